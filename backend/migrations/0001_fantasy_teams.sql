@@ -0,0 +1,35 @@
+-- Priced driver and constructor assets that can be picked for a season.
+-- Drivers are referenced by their car number, constructors by their Ergast constructorId.
+CREATE TABLE IF NOT EXISTS "FantasyAssets" (
+    id SERIAL PRIMARY KEY,
+    season TEXT NOT NULL,
+    asset_type TEXT NOT NULL CHECK (asset_type IN ('driver', 'constructor')),
+    external_ref TEXT NOT NULL,
+    name TEXT NOT NULL,
+    constructor_ref TEXT,
+    price DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (season, asset_type, external_ref)
+);
+
+-- One fantasy team per user (Claims.sub) per season.
+CREATE TABLE IF NOT EXISTS "FantasyTeams" (
+    id SERIAL PRIMARY KEY,
+    user_email TEXT NOT NULL,
+    season TEXT NOT NULL,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (user_email, season)
+);
+
+-- Picks of a team for a given round. The latest row is the current team.
+CREATE TABLE IF NOT EXISTS "FantasyTeamRounds" (
+    team_id INT NOT NULL REFERENCES "FantasyTeams" (id) ON DELETE CASCADE,
+    round INT NOT NULL,
+    driver_ids INT[] NOT NULL,
+    constructor_ids INT[] NOT NULL,
+    team_value DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (team_id, round)
+);
@@ -0,0 +1,50 @@
+-- Versioned scoring rules per session type. The highest active version is used.
+CREATE TABLE IF NOT EXISTS "FantasyScoringRules" (
+    version INT NOT NULL,
+    session_type TEXT NOT NULL,
+    rules JSONB NOT NULL,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (version, session_type)
+);
+
+INSERT INTO "FantasyScoringRules" (version, session_type, rules) VALUES
+    (1, 'Qualifying', '{
+        "position_points": [10, 9, 8, 7, 6, 5, 4, 3, 2, 1],
+        "position_gained": 0,
+        "position_lost": 0,
+        "fastest_lap": 0,
+        "dnf": -5,
+        "beat_teammate": 2
+    }'),
+    (1, 'Sprint', '{
+        "position_points": [8, 7, 6, 5, 4, 3, 2, 1],
+        "position_gained": 1,
+        "position_lost": -1,
+        "fastest_lap": 5,
+        "dnf": -20,
+        "beat_teammate": 2
+    }'),
+    (1, 'Race', '{
+        "position_points": [25, 18, 15, 12, 10, 8, 6, 4, 2, 1],
+        "position_gained": 1,
+        "position_lost": -1,
+        "fastest_lap": 10,
+        "dnf": -20,
+        "beat_teammate": 3
+    }')
+ON CONFLICT DO NOTHING;
+
+-- Points earned by each asset in a scored session.
+CREATE TABLE IF NOT EXISTS "FantasyPoints" (
+    id SERIAL PRIMARY KEY,
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    session_type TEXT NOT NULL,
+    asset_id INT NOT NULL REFERENCES "FantasyAssets" (id) ON DELETE CASCADE,
+    points DOUBLE PRECISION NOT NULL,
+    breakdown JSONB NOT NULL,
+    rules_version INT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE (season, round, session_type, asset_id)
+);
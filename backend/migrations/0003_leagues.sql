@@ -0,0 +1,27 @@
+-- Total fantasy points of a team in a scored round.
+CREATE TABLE IF NOT EXISTS "FantasyTeamScores" (
+    team_id INT NOT NULL REFERENCES "FantasyTeams" (id) ON DELETE CASCADE,
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    points DOUBLE PRECISION NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (team_id, round)
+);
+
+CREATE TABLE IF NOT EXISTS "Leagues" (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    season TEXT NOT NULL,
+    is_public BOOLEAN NOT NULL DEFAULT false,
+    invite_code TEXT NOT NULL UNIQUE,
+    owner_email TEXT NOT NULL,
+    is_closed BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE IF NOT EXISTS "LeagueMembers" (
+    league_id INT NOT NULL REFERENCES "Leagues" (id) ON DELETE CASCADE,
+    user_email TEXT NOT NULL,
+    joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (league_id, user_email)
+);
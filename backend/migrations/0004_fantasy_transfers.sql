@@ -0,0 +1,20 @@
+-- Transfer accounting of a team for a round.
+ALTER TABLE "FantasyTeamRounds"
+    ADD COLUMN IF NOT EXISTS free_transfers INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS transfers_made INT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS penalty_points DOUBLE PRECISION NOT NULL DEFAULT 0;
+
+-- Audit log of every asset swapped in or out of a team.
+CREATE TABLE IF NOT EXISTS "FantasyTransfers" (
+    id SERIAL PRIMARY KEY,
+    team_id INT NOT NULL REFERENCES "FantasyTeams" (id) ON DELETE CASCADE,
+    user_email TEXT NOT NULL,
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    asset_out INT NOT NULL REFERENCES "FantasyAssets" (id),
+    asset_in INT NOT NULL REFERENCES "FantasyAssets" (id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS "FantasyTransfers_team_round_idx"
+    ON "FantasyTransfers" (team_id, round);
@@ -0,0 +1,9 @@
+-- Chip played by a team in a round, plus the driver boosted by extra_boost.
+ALTER TABLE "FantasyTeamRounds"
+    ADD COLUMN IF NOT EXISTS chip TEXT CHECK (chip IN ('wildcard', 'limitless', 'extra_boost')),
+    ADD COLUMN IF NOT EXISTS boost_asset_id INT REFERENCES "FantasyAssets" (id);
+
+-- Every chip can only be played once per season.
+CREATE UNIQUE INDEX IF NOT EXISTS "FantasyTeamRounds_team_chip_idx"
+    ON "FantasyTeamRounds" (team_id, chip)
+    WHERE chip IS NOT NULL;
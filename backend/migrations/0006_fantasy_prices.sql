@@ -0,0 +1,12 @@
+-- Price of every asset after each scored round.
+CREATE TABLE IF NOT EXISTS "FantasyAssetPrices" (
+    asset_id INT NOT NULL REFERENCES "FantasyAssets" (id) ON DELETE CASCADE,
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    price DOUBLE PRECISION NOT NULL,
+    delta DOUBLE PRECISION NOT NULL,
+    rolling_points DOUBLE PRECISION NOT NULL,
+    ownership DOUBLE PRECISION NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (asset_id, round)
+);
@@ -0,0 +1,11 @@
+-- Sessions the key reconciler could not match to an OpenF1 session_key.
+-- Rows are removed once the session resolves; delete a row to retry it.
+CREATE TABLE IF NOT EXISTS "UnresolvedSessions" (
+    session_id INT PRIMARY KEY REFERENCES "Sessions" (id) ON DELETE CASCADE,
+    race_id BIGINT NOT NULL,
+    session_type TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
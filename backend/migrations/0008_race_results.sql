@@ -0,0 +1,9 @@
+-- Final race classifications, stored as the Jolpica race payload so they can
+-- be served without Jolpica once the results have settled.
+CREATE TABLE IF NOT EXISTS "RaceResults" (
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    race JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (season, round)
+);
@@ -0,0 +1,27 @@
+-- Final qualifying and sprint classifications, next to "RaceResults". Each
+-- row holds the Jolpica payload and the OpenF1 classification of the session,
+-- whichever has been stored so far.
+ALTER TABLE "RaceResults"
+    ALTER COLUMN race DROP NOT NULL,
+    ADD COLUMN IF NOT EXISTS session_key INT UNIQUE,
+    ADD COLUMN IF NOT EXISTS session_results JSONB;
+
+CREATE TABLE IF NOT EXISTS "QualifyingResults" (
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    race JSONB,
+    session_key INT UNIQUE,
+    session_results JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (season, round)
+);
+
+CREATE TABLE IF NOT EXISTS "SprintResults" (
+    season TEXT NOT NULL,
+    round INT NOT NULL,
+    race JSONB,
+    session_key INT UNIQUE,
+    session_results JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (season, round)
+);
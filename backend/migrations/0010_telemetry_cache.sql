@@ -0,0 +1,14 @@
+-- Second cache tier behind the in-memory cache, for values computed from
+-- OpenF1 telemetry. Rows of completed sessions have no expiry.
+CREATE TABLE IF NOT EXISTS "TelemetryCache" (
+    namespace TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value JSONB NOT NULL,
+    expires_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (namespace, key)
+);
+
+CREATE INDEX IF NOT EXISTS "TelemetryCache_expires_at_idx"
+    ON "TelemetryCache" (expires_at)
+    WHERE expires_at IS NOT NULL;
@@ -0,0 +1,12 @@
+-- Raw OpenF1 data of finished sessions, as gzip compressed JSON arrays.
+-- car_data and location are stored per driver, other datasets cover the
+-- whole session with driver_number 0.
+CREATE TABLE IF NOT EXISTS "SessionArchive" (
+    session_key INT NOT NULL,
+    dataset TEXT NOT NULL,
+    driver_number INT NOT NULL DEFAULT 0,
+    payload BYTEA NOT NULL,
+    row_count INT NOT NULL,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (session_key, dataset, driver_number)
+);
@@ -0,0 +1,2 @@
+-- Operators created with `f1ctl create-admin`.
+ALTER TABLE "Users" ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT false;
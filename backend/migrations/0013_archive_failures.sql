@@ -0,0 +1,9 @@
+-- Sessions the archiver failed to store. Rows are removed once the session
+-- is archived; delete a row to retry a session that ran out of attempts.
+CREATE TABLE IF NOT EXISTS "ArchiveFailures" (
+    session_key INT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
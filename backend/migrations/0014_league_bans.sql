@@ -0,0 +1,8 @@
+-- Users kicked from a league, who can no longer join it with its invite
+-- code. Delete a row to let the user back in.
+CREATE TABLE IF NOT EXISTS "LeagueBans" (
+    league_id INT NOT NULL REFERENCES "Leagues" (id) ON DELETE CASCADE,
+    user_email TEXT NOT NULL,
+    banned_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (league_id, user_email)
+);
@@ -0,0 +1,6 @@
+-- Price each pick of a round was bought at, by asset id. Assets kept from
+-- earlier rounds count at this price towards the budget cap, so price rises
+-- never push a saved team over it. Rows from before this column fall back
+-- to current prices.
+ALTER TABLE "FantasyTeamRounds"
+    ADD COLUMN IF NOT EXISTS purchase_prices JSONB NOT NULL DEFAULT '{}';
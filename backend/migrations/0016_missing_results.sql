@@ -0,0 +1,13 @@
+-- Settled sessions whose final results could not be archived yet, e.g.
+-- cancelled rounds or upstream errors. Sessions are no longer fetched after
+-- a number of attempts; delete a row to retry it.
+CREATE TABLE IF NOT EXISTS "MissingResults" (
+    season TEXT NOT NULL,
+    round TEXT NOT NULL,
+    session_type TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    attempts INT NOT NULL DEFAULT 1,
+    first_seen_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_attempt_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (season, round, session_type)
+);
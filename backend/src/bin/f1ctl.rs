@@ -0,0 +1,332 @@
+//! Maintenance commands run against the same database and upstream APIs as the
+//! server, without going through authenticated endpoints.
+
+use std::{collections::HashMap, error::Error, io::BufRead, process::ExitCode, sync::Arc};
+
+use axum::extract::{Path, State};
+use backend::{
+    handlers::session::{
+        get_drivers_position_telemetry, get_pit_stops, get_sector_timings, get_session_data,
+        get_stints,
+    },
+    routes::{build_state, init_tracing},
+    services::{
+        archive::archive_session, calendar_sync::sync_calendar, results::archive_final_results,
+        scoring::score_round,
+    },
+    utils::{config::Config, hash_password::hash_password, state::AppState},
+};
+use chrono::{Datelike, Utc};
+
+const USAGE: &str = "\
+Usage: f1ctl <command> [options]
+
+Commands:
+  sync-calendar [--season <year>]        Sync races, circuits and sessions from Jolpica
+  backfill-results --from <year> [--to <year>]
+                                         Sync calendars and store final results
+  archive-session <session_key>          Download a session from OpenF1 into the archive
+  score-round <year> <round>             Score fantasy points of a round
+  warm-cache <session_key>               Compute the cached telemetry of a session
+  create-admin --email <email> [--username <name>] [--name <name>] [--password -]
+                                         Create an admin user, or promote an existing one.
+                                         New users are asked for a password on stdin.
+                                         Existing users keep theirs unless --password is
+                                         given, `--password -` reads the new one from stdin.
+                                         Avoid passing it as a value, it ends up in the
+                                         shell history.";
+
+type CliResult = Result<(), Box<dyn Error>>;
+
+/// Positional arguments and `--flag value` / `--flag=value` options.
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut args = args.peekable();
+
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                positional.push(arg);
+                continue;
+            };
+            let (name, value) = match flag.split_once('=') {
+                Some((name, value)) => (name.to_string(), value.to_string()),
+                None => {
+                    let value = args
+                        .next_if(|v| !v.starts_with("--"))
+                        .ok_or_else(|| format!("--{flag} needs a value"))?;
+                    (flag.to_string(), value)
+                }
+            };
+            options.insert(name, value);
+        }
+
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn positional(&self, idx: usize, name: &str) -> Result<&str, String> {
+        self.positional
+            .get(idx)
+            .map(String::as_str)
+            .ok_or_else(|| format!("missing <{name}>"))
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn required(&self, name: &str) -> Result<&str, String> {
+        self.option(name).ok_or_else(|| format!("missing --{name}"))
+    }
+}
+
+fn parse_num<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid {name}: {value}"))
+}
+
+async fn sync_calendar_cmd(state: &AppState, args: &Args) -> CliResult {
+    let season = match args.option("season") {
+        Some(season) => season.to_string(),
+        None => Utc::now().year().to_string(),
+    };
+
+    let report = sync_calendar(state, &season).await?;
+    for change in &report.changes {
+        println!("{change}");
+    }
+    println!(
+        "{}: {} circuits, {} races, {} sessions changed",
+        season, report.circuits_changed, report.races_changed, report.sessions_changed
+    );
+    Ok(())
+}
+
+async fn backfill_results(state: &AppState, args: &Args) -> CliResult {
+    let from: i32 = parse_num(args.required("from")?, "--from")?;
+    let to: i32 = match args.option("to") {
+        Some(to) => parse_num(to, "--to")?,
+        None => from,
+    };
+
+    for season in from..=to {
+        let season = season.to_string();
+        sync_calendar(state, &season).await?;
+        let stored = archive_final_results(state, &season).await?;
+        println!("{season}: stored results of {stored} sessions");
+    }
+    Ok(())
+}
+
+async fn archive_session_cmd(state: &AppState, args: &Args) -> CliResult {
+    let session_key: u32 = parse_num(args.positional(0, "session_key")?, "session_key")?;
+
+    let report = archive_session(state, session_key).await?;
+    if report.skipped {
+        println!("Session {session_key} is already archived");
+    } else {
+        println!(
+            "Archived session {}: {} drivers, {} rows",
+            session_key, report.drivers, report.rows
+        );
+    }
+    Ok(())
+}
+
+async fn score_round_cmd(state: &AppState, args: &Args) -> CliResult {
+    let season = args.positional(0, "year")?;
+    let round: i32 = parse_num(args.positional(1, "round")?, "round")?;
+
+    let sessions = score_round(state, season, round).await?;
+    println!("{}", serde_json::to_string_pretty(&sessions)?);
+    Ok(())
+}
+
+/// Runs the cached session handlers once so the first users after a session
+/// do not wait for OpenF1.
+async fn warm_cache(state: Arc<AppState>, args: &Args) -> CliResult {
+    let session_key: u32 = parse_num(args.positional(0, "session_key")?, "session_key")?;
+
+    let results = [
+        (
+            "session results",
+            get_session_data(State(state.clone()), Path(session_key))
+                .await
+                .map(|_| ()),
+        ),
+        (
+            "position graph",
+            get_drivers_position_telemetry(State(state.clone()), Path(session_key))
+                .await
+                .map(|_| ()),
+        ),
+        (
+            "sector timings",
+            get_sector_timings(State(state.clone()), Path(session_key))
+                .await
+                .map(|_| ()),
+        ),
+        (
+            "pit stops",
+            get_pit_stops(State(state.clone()), Path(session_key))
+                .await
+                .map(|_| ()),
+        ),
+        (
+            "stints",
+            get_stints(State(state.clone()), Path(session_key))
+                .await
+                .map(|_| ()),
+        ),
+    ];
+
+    let mut failed = false;
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{name}: ok"),
+            Err(e) => {
+                failed = true;
+                println!("{name}: failed, {e}");
+            }
+        }
+    }
+
+    if failed {
+        return Err("some caches could not be warmed".into());
+    }
+    Ok(())
+}
+
+fn prompt_password() -> Result<String, Box<dyn Error>> {
+    eprint!("Password: ");
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        return Err("password must not be empty".into());
+    }
+    Ok(password)
+}
+
+/// Password given with `--password`, `-` reading it from stdin.
+fn password_option(args: &Args) -> Result<Option<String>, Box<dyn Error>> {
+    match args.option("password") {
+        None => Ok(None),
+        Some("-") => prompt_password().map(Some),
+        Some("") => Err("password must not be empty".into()),
+        Some(password) => Ok(Some(password.to_string())),
+    }
+}
+
+async fn create_admin(state: &AppState, args: &Args) -> CliResult {
+    let email = args.required("email")?;
+    let password = password_option(args)?;
+
+    let existing: Option<String> =
+        sqlx::query_scalar(r#"SELECT email FROM "Users" WHERE LOWER(email) = LOWER($1)"#)
+            .bind(email)
+            .fetch_optional(&state.db_pool)
+            .await?;
+
+    match existing {
+        Some(existing) => {
+            let hashed = password.as_deref().map(hash_password).transpose()?;
+            sqlx::query(
+                r#"
+                UPDATE "Users"
+                SET is_admin = true, hashed_password = COALESCE($1, hashed_password)
+                WHERE email = $2
+                "#,
+            )
+            .bind(&hashed)
+            .bind(&existing)
+            .execute(&state.db_pool)
+            .await?;
+            match hashed {
+                Some(_) => println!("Promoted {existing} to admin and changed the password"),
+                None => println!("Promoted {existing} to admin"),
+            }
+        }
+        None => {
+            let password = match password {
+                Some(password) => password,
+                None => prompt_password()?,
+            };
+            let hashed = hash_password(&password)?;
+            sqlx::query(
+                r#"
+                INSERT INTO "Users" (name, username, email, hashed_password, auth_provider, is_admin)
+                VALUES ($1, $2, $3, $4, 'email', true)
+                "#,
+            )
+            .bind(args.option("name"))
+            .bind(args.option("username"))
+            .bind(email)
+            .bind(&hashed)
+            .execute(&state.db_pool)
+            .await?;
+            println!("Created admin {email}");
+        }
+    }
+    Ok(())
+}
+
+async fn run(command: &str, args: Args) -> CliResult {
+    let state = build_state(Config::init()).await?;
+
+    match command {
+        "sync-calendar" => sync_calendar_cmd(&state, &args).await,
+        "backfill-results" => backfill_results(&state, &args).await,
+        "archive-session" => archive_session_cmd(&state, &args).await,
+        "score-round" => score_round_cmd(&state, &args).await,
+        "warm-cache" => warm_cache(state, &args).await,
+        "create-admin" => create_admin(&state, &args).await,
+        _ => unreachable!(),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenv::dotenv().ok();
+
+    let mut argv = std::env::args().skip(1);
+    let command = argv.next().unwrap_or_default();
+    if !matches!(
+        command.as_str(),
+        "sync-calendar"
+            | "backfill-results"
+            | "archive-session"
+            | "score-round"
+            | "warm-cache"
+            | "create-admin"
+    ) {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    }
+    let args = match Args::parse(argv) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("f1ctl: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    init_tracing();
+    match run(&command, args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("f1ctl {command}: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::fmt;
+
+use http::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use tracing::debug;
+
+use crate::models::jolpica::{Race, RaceTable, StandingsList, StandingsTable};
+
+/// Largest page Jolpica serves, bigger limits are silently capped.
+const PAGE_SIZE: u32 = 100;
+
+#[derive(Debug)]
+pub enum JolpicaError {
+    Request(reqwest::Error),
+    Status(StatusCode, String),
+    Decode(serde_json::Error),
+}
+
+impl fmt::Display for JolpicaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JolpicaError::Request(e) => write!(f, "Jolpica request failed: {e}"),
+            JolpicaError::Status(code, body) => write!(f, "Jolpica returned {code}: {body}"),
+            JolpicaError::Decode(e) => write!(f, "Unexpected Jolpica payload: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for JolpicaError {}
+
+/// Table wrapped in `MRData` that can be fetched page by page.
+pub trait Table: DeserializeOwned {
+    const KEY: &'static str;
+
+    /// Appends the next page. Limits apply to result rows, so a race or
+    /// standings list can be split over two pages and is joined back here.
+    fn append(&mut self, next: Self);
+}
+
+impl Table for RaceTable {
+    const KEY: &'static str = "RaceTable";
+
+    fn append(&mut self, next: Self) {
+        for race in next.races {
+            match self.races.last_mut() {
+                Some(last) if last.season == race.season && last.round == race.round => {
+                    last.results.extend(race.results);
+                    last.sprint_results.extend(race.sprint_results);
+                    last.qualifying_results.extend(race.qualifying_results);
+                }
+                _ => self.races.push(race),
+            }
+        }
+    }
+}
+
+impl Table for StandingsTable {
+    const KEY: &'static str = "StandingsTable";
+
+    fn append(&mut self, next: Self) {
+        for list in next.standings_lists {
+            match self.standings_lists.last_mut() {
+                Some(last) if last.season == list.season && last.round == list.round => {
+                    last.driver_standings.extend(list.driver_standings);
+                    last.constructor_standings
+                        .extend(list.constructor_standings);
+                }
+                _ => self.standings_lists.push(list),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JolpicaClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl JolpicaClient {
+    pub fn new(http: reqwest::Client, base_url: &str) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get_page<T: Table>(&self, path: &str, offset: u32) -> Result<(T, u32), JolpicaError> {
+        let url = format!(
+            "{}/{path}/?format=json&limit={PAGE_SIZE}&offset={offset}",
+            self.base_url
+        );
+        debug!("GET {}", url);
+
+        let res = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .map_err(JolpicaError::Request)?;
+        let status = res.status();
+        if !status.is_success() {
+            let body = res.text().await.unwrap_or_default();
+            return Err(JolpicaError::Status(status, body));
+        }
+
+        let mut body: Value = res.json().await.map_err(JolpicaError::Request)?;
+        let data = &mut body["MRData"];
+        let total = data["total"]
+            .as_str()
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(0);
+        let table = serde_json::from_value(data[T::KEY].take()).map_err(JolpicaError::Decode)?;
+
+        Ok((table, total))
+    }
+
+    /// Fetches every page of `path` and joins them into one table.
+    pub async fn get_all<T: Table>(&self, path: &str) -> Result<T, JolpicaError> {
+        let (mut table, total) = self.get_page::<T>(path, 0).await?;
+
+        let mut offset = PAGE_SIZE;
+        while offset < total {
+            let (page, _) = self.get_page::<T>(path, offset).await?;
+            table.append(page);
+            offset += PAGE_SIZE;
+        }
+
+        Ok(table)
+    }
+
+    async fn races(&self, path: &str) -> Result<Vec<Race>, JolpicaError> {
+        Ok(self.get_all::<RaceTable>(path).await?.races)
+    }
+
+    async fn race(&self, path: &str) -> Result<Option<Race>, JolpicaError> {
+        Ok(self.races(path).await?.into_iter().next())
+    }
+
+    /// Race calendar of a season, with session times.
+    pub async fn schedule(&self, season: &str) -> Result<Vec<Race>, JolpicaError> {
+        self.races(&format!("{season}/races")).await
+    }
+
+    /// Race classification. `round` can also be "last".
+    pub async fn results(&self, season: &str, round: &str) -> Result<Option<Race>, JolpicaError> {
+        self.race(&format!("{season}/{round}/results")).await
+    }
+
+    pub async fn sprint_results(
+        &self,
+        season: &str,
+        round: &str,
+    ) -> Result<Option<Race>, JolpicaError> {
+        self.race(&format!("{season}/{round}/sprint")).await
+    }
+
+    pub async fn qualifying(
+        &self,
+        season: &str,
+        round: &str,
+    ) -> Result<Option<Race>, JolpicaError> {
+        self.race(&format!("{season}/{round}/qualifying")).await
+    }
+
+    async fn standings(&self, path: &str) -> Result<Option<StandingsList>, JolpicaError> {
+        let table = self.get_all::<StandingsTable>(path).await?;
+        Ok(table.standings_lists.into_iter().next())
+    }
+
+    pub async fn driver_standings(
+        &self,
+        season: &str,
+    ) -> Result<Option<StandingsList>, JolpicaError> {
+        self.standings(&format!("{season}/driverstandings")).await
+    }
+
+    pub async fn constructor_standings(
+        &self,
+        season: &str,
+    ) -> Result<Option<StandingsList>, JolpicaError> {
+        self.standings(&format!("{season}/constructorstandings"))
+            .await
+    }
+}
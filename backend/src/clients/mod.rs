@@ -0,0 +1,2 @@
+pub mod jolpica;
+pub mod openf1;
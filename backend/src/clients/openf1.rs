@@ -0,0 +1,221 @@
+use std::fmt;
+
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use http::StatusCode;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+use crate::models::openf1::{
+    CarData, Interval, Lap, Location, PitStop, Position, RaceControlMessage, SessionDriver,
+    SessionInfo, SessionResult, Stint, Weather,
+};
+
+#[derive(Debug)]
+pub enum OpenF1Error {
+    Request(reqwest::Error),
+    Status(StatusCode, String),
+    Decode(serde_json::Error),
+}
+
+impl fmt::Display for OpenF1Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OpenF1Error::Request(e) => write!(f, "OpenF1 request failed: {e}"),
+            OpenF1Error::Status(code, body) => write!(f, "OpenF1 returned {code}: {body}"),
+            OpenF1Error::Decode(e) => write!(f, "Unexpected OpenF1 payload: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for OpenF1Error {}
+
+/// Query filters as (field, operator, value). They are written into the URL
+/// as is since OpenF1 does not accept encoded comparison operators.
+type Filters = Vec<(&'static str, &'static str, String)>;
+
+/// Time range of samples as (from, to), both exclusive.
+pub type Window = (DateTime<Utc>, DateTime<Utc>);
+
+fn timestamp(date: DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenF1Client {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl OpenF1Client {
+    pub fn new(http: reqwest::Client, base_url: &str) -> Self {
+        Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        filters: Filters,
+    ) -> Result<Vec<T>, OpenF1Error> {
+        let query = filters
+            .iter()
+            .map(|(field, op, value)| format!("{field}{op}{value}"))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = format!("{}/{endpoint}?{query}", self.base_url);
+        debug!("GET {}", url);
+
+        let res = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .map_err(OpenF1Error::Request)?;
+        let status = res.status();
+        let body = res.text().await.map_err(OpenF1Error::Request)?;
+
+        // OpenF1 answers 404 when a filter matches nothing
+        if status == StatusCode::NOT_FOUND {
+            return Ok(Vec::new());
+        }
+        if !status.is_success() {
+            return Err(OpenF1Error::Status(status, body));
+        }
+
+        serde_json::from_str(&body).map_err(OpenF1Error::Decode)
+    }
+
+    fn session_filters(session_key: u32, driver_number: Option<u32>) -> Filters {
+        let mut filters = vec![("session_key", "=", session_key.to_string())];
+        if let Some(driver) = driver_number {
+            filters.push(("driver_number", "=", driver.to_string()));
+        }
+        filters
+    }
+
+    fn window_filters(session_key: u32, driver_number: u32, window: Option<Window>) -> Filters {
+        let mut filters = Self::session_filters(session_key, Some(driver_number));
+        if let Some((from, to)) = window {
+            filters.push(("date", ">", timestamp(from)));
+            filters.push(("date", "<", timestamp(to)));
+        }
+        filters
+    }
+
+    pub async fn laps(
+        &self,
+        session_key: u32,
+        driver_number: Option<u32>,
+    ) -> Result<Vec<Lap>, OpenF1Error> {
+        self.get("laps", Self::session_filters(session_key, driver_number))
+            .await
+    }
+
+    /// Car telemetry of a driver, for the whole session without `window`.
+    pub async fn car_data(
+        &self,
+        session_key: u32,
+        driver_number: u32,
+        window: Option<Window>,
+    ) -> Result<Vec<CarData>, OpenF1Error> {
+        self.get(
+            "car_data",
+            Self::window_filters(session_key, driver_number, window),
+        )
+        .await
+    }
+
+    pub async fn location(
+        &self,
+        session_key: u32,
+        driver_number: u32,
+        window: Option<Window>,
+    ) -> Result<Vec<Location>, OpenF1Error> {
+        self.get(
+            "location",
+            Self::window_filters(session_key, driver_number, window),
+        )
+        .await
+    }
+
+    pub async fn position(&self, session_key: u32) -> Result<Vec<Position>, OpenF1Error> {
+        self.get("position", Self::session_filters(session_key, None))
+            .await
+    }
+
+    /// Classification of a session, optionally only down to `max_position`.
+    pub async fn session_result(
+        &self,
+        session_key: u32,
+        max_position: Option<u32>,
+    ) -> Result<Vec<SessionResult>, OpenF1Error> {
+        let mut filters = Self::session_filters(session_key, None);
+        if let Some(position) = max_position {
+            filters.push(("position", "<=", position.to_string()));
+        }
+        self.get("session_result", filters).await
+    }
+
+    /// Sessions of a year starting between `from` and `to`.
+    pub async fn sessions(
+        &self,
+        year: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<SessionInfo>, OpenF1Error> {
+        let filters = vec![
+            ("year", "=", year.to_string()),
+            ("date_start", ">=", from.to_string()),
+            ("date_start", "<=", to.to_string()),
+        ];
+        self.get("sessions", filters).await
+    }
+
+    /// Weather samples of a session. Both keys also accept "latest".
+    pub async fn weather(
+        &self,
+        meeting_key: &str,
+        session_key: &str,
+    ) -> Result<Vec<Weather>, OpenF1Error> {
+        let filters = vec![
+            ("meeting_key", "=", meeting_key.to_string()),
+            ("session_key", "=", session_key.to_string()),
+        ];
+        self.get("weather", filters).await
+    }
+
+    pub async fn session_weather(&self, session_key: u32) -> Result<Vec<Weather>, OpenF1Error> {
+        self.get("weather", Self::session_filters(session_key, None))
+            .await
+    }
+
+    pub async fn drivers(&self, session_key: u32) -> Result<Vec<SessionDriver>, OpenF1Error> {
+        self.get("drivers", Self::session_filters(session_key, None))
+            .await
+    }
+
+    pub async fn stints(&self, session_key: u32) -> Result<Vec<Stint>, OpenF1Error> {
+        self.get("stints", Self::session_filters(session_key, None))
+            .await
+    }
+
+    pub async fn pit(&self, session_key: u32) -> Result<Vec<PitStop>, OpenF1Error> {
+        self.get("pit", Self::session_filters(session_key, None))
+            .await
+    }
+
+    pub async fn intervals(&self, session_key: u32) -> Result<Vec<Interval>, OpenF1Error> {
+        self.get("intervals", Self::session_filters(session_key, None))
+            .await
+    }
+
+    pub async fn race_control(
+        &self,
+        session_key: u32,
+    ) -> Result<Vec<RaceControlMessage>, OpenF1Error> {
+        self.get("race_control", Self::session_filters(session_key, None))
+            .await
+    }
+}
@@ -45,10 +45,10 @@ pub async fn register(
         RETURNING *
         "#,
     )
-    .bind(&payload["name"].as_str().unwrap())   
-    .bind(&payload["username"].as_str().unwrap())
-    .bind(&payload["email"].as_str().unwrap())
-    .bind(&payload["dob"].as_str().unwrap())
+    .bind(payload["name"].as_str().unwrap())   
+    .bind(payload["username"].as_str().unwrap())
+    .bind(payload["email"].as_str().unwrap())
+    .bind(payload["dob"].as_str().unwrap())
     .bind(&hashed)
     .bind("email")
     .fetch_one(&state.db_pool)
@@ -137,9 +137,9 @@ pub async fn login(
                 .verify_password(password.as_bytes(), &parsed_hash)
                 .is_ok()
             {
-                let token = jwt_encode(payload["email"].to_string(), state.config.jwt_secret.as_ref());
+                let token = jwt_encode(user.email.clone(), state.config.jwt_secret.as_ref());
                 let refresh_token =
-                    refresh_token_encode(payload["email"].to_string(), state.config.jwt_secret.as_ref());
+                    refresh_token_encode(user.email.clone(), state.config.jwt_secret.as_ref());
 
                 (
                     StatusCode::OK,
@@ -183,7 +183,7 @@ pub async fn refresh_token_handler(
 ) -> impl IntoResponse {
     let token_data: Result<jsonwebtoken::TokenData<RefreshClaims>, jsonwebtoken::errors::Error> =
         jsonwebtoken::decode::<RefreshClaims>(
-            &payload["refresh_token"].as_str().unwrap_or_default(),
+            payload["refresh_token"].as_str().unwrap_or_default(),
             &DecodingKey::from_secret(state.config.jwt_secret.as_bytes()),
             &Validation::default(),
         );
@@ -0,0 +1,671 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    models::{
+        error::Error,
+        fantasy::{
+            AssetPrice, AssetPriceTrend, ChipPayload, FantasyAsset, FantasyTeam,
+            FantasyTeamPayload, FantasyTeamRound, FantasyTransfer, TeamHistoryRound, TransferQuery,
+            CHIPS, CHIP_EXTRA_BOOST, CHIP_LIMITLESS,
+        },
+        jwt::Claims,
+        scoring::FantasyPoints,
+    },
+    services::{
+        pricing::ROLLING_ROUNDS,
+        schedule::{ensure_unlocked, next_round},
+        scoring::score_round,
+    },
+    utils::{
+        fantasy::{
+            budget_cap, free_transfers, purchase_prices, swapped_assets, transfer_penalty,
+            validate_picks,
+        },
+        state::AppState,
+    },
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use http::StatusCode;
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+
+async fn find_team(
+    db: &PgPool,
+    user_email: &str,
+    season: &str,
+) -> Result<Option<FantasyTeam>, Error> {
+    let team = sqlx::query_as::<_, FantasyTeam>(
+        r#"SELECT * FROM "FantasyTeams" WHERE user_email = $1 AND season = $2"#,
+    )
+    .bind(user_email)
+    .bind(season)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(team)
+}
+
+async fn fetch_assets(db: &PgPool, season: &str, ids: &[i32]) -> Result<Vec<FantasyAsset>, Error> {
+    let assets = sqlx::query_as::<_, FantasyAsset>(
+        r#"SELECT * FROM "FantasyAssets" WHERE season = $1 AND id = ANY($2)"#,
+    )
+    .bind(season)
+    .bind(ids)
+    .fetch_all(db)
+    .await?;
+
+    Ok(assets)
+}
+
+/// Picks saved for exactly `round`, if any.
+async fn round_picks<'e, E: PgExecutor<'e>>(
+    db: E,
+    team_id: i32,
+    round: i32,
+) -> Result<Option<FantasyTeamRound>, Error> {
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"SELECT * FROM "FantasyTeamRounds" WHERE team_id = $1 AND round = $2"#,
+    )
+    .bind(team_id)
+    .bind(round)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(picks)
+}
+
+/// The team as it entered `round`. Picks made with the limitless chip are
+/// skipped, the team reverts to what it was before that round.
+async fn previous_picks<'e, E: PgExecutor<'e>>(
+    db: E,
+    team_id: i32,
+    round: i32,
+) -> Result<Option<FantasyTeamRound>, Error> {
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        SELECT * FROM "FantasyTeamRounds"
+        WHERE team_id = $1 AND round < $2 AND chip IS DISTINCT FROM $3
+        ORDER BY round DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(team_id)
+    .bind(round)
+    .bind(CHIP_LIMITLESS)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(picks)
+}
+
+async fn team_response(
+    db: &PgPool,
+    team: &FantasyTeam,
+    picks: &FantasyTeamRound,
+) -> Result<serde_json::Value, Error> {
+    let ids: Vec<i32> = picks
+        .driver_ids
+        .iter()
+        .chain(picks.constructor_ids.iter())
+        .copied()
+        .collect();
+    let assets = fetch_assets(db, &team.season, &ids).await?;
+
+    let pick = |ids: &[i32]| -> Vec<FantasyAsset> {
+        ids.iter()
+            .filter_map(|id| assets.iter().find(|a| a.id == *id).cloned())
+            .collect()
+    };
+
+    Ok(json!({
+        "team": team,
+        "round": picks.round,
+        "drivers": pick(&picks.driver_ids),
+        "constructors": pick(&picks.constructor_ids),
+        "team_value": picks.team_value,
+        "budget_cap": budget_cap(picks.chip.as_deref()),
+        "free_transfers": picks.free_transfers,
+        "transfers_made": picks.transfers_made,
+        "penalty_points": picks.penalty_points,
+        "chip": picks.chip,
+        "boost_asset_id": picks.boost_asset_id,
+    }))
+}
+
+pub async fn get_assets(
+    State(state): State<Arc<AppState>>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let assets = sqlx::query_as::<_, FantasyAsset>(
+        r#"
+        SELECT * FROM "FantasyAssets"
+        WHERE season = $1
+        ORDER BY asset_type DESC, price DESC
+        "#,
+    )
+    .bind(&season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": assets }))))
+}
+
+pub async fn get_team(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let team = find_team(&state.db_pool, &claims.sub, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No fantasy team for this season"))?;
+
+    // Once the season is over there is no next round, show the last picks
+    let round = next_round(&state.db_pool, &season)
+        .await?
+        .unwrap_or(i32::MAX);
+    let picks = match round_picks(&state.db_pool, team.id, round).await? {
+        Some(picks) => picks,
+        None => previous_picks(&state.db_pool, team.id, round)
+            .await?
+            .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "Team has no picks yet"))?,
+    };
+
+    let body = team_response(&state.db_pool, &team, &picks).await?;
+    Ok((StatusCode::OK, Json(body)))
+}
+
+pub async fn save_team(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+    Json(payload): Json<FantasyTeamPayload>,
+) -> Result<impl IntoResponse, Error> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(Error::new(StatusCode::BAD_REQUEST, "Team name is required"));
+    }
+
+    let round = next_round(&state.db_pool, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "No upcoming round for this season"))?;
+
+    let ids: Vec<i32> = payload
+        .driver_ids
+        .iter()
+        .chain(payload.constructor_ids.iter())
+        .copied()
+        .collect();
+    let assets = fetch_assets(&state.db_pool, &season, &ids).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let team = sqlx::query_as::<_, FantasyTeam>(
+        r#"
+        INSERT INTO "FantasyTeams" (user_email, season, name)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (user_email, season)
+        DO UPDATE SET name = EXCLUDED.name, updated_at = now()
+        RETURNING *
+        "#,
+    )
+    .bind(&claims.sub)
+    .bind(&season)
+    .bind(name)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // The team as it entered this round, and as last saved for this round
+    let previous = previous_picks(&mut *tx, team.id, round).await?;
+    let current = round_picks(&mut *tx, team.id, round).await?;
+    let chip = current.as_ref().and_then(|c| c.chip.clone());
+
+    // Assets kept from the last saved picks count at what was paid for them
+    let held = current
+        .as_ref()
+        .or(previous.as_ref())
+        .map(|picks| picks.purchase_prices.0.clone())
+        .unwrap_or_default();
+    let team_value = validate_picks(
+        &assets,
+        &payload.driver_ids,
+        &payload.constructor_ids,
+        &held,
+        budget_cap(chip.as_deref()),
+    )
+    .map_err(|e| Error::new(StatusCode::BAD_REQUEST, &e))?;
+    let prices = purchase_prices(&assets, &ids, &held);
+
+    if let Some(boosted) = current.as_ref().and_then(|c| c.boost_asset_id) {
+        if !payload.driver_ids.contains(&boosted) {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                "The boosted driver cannot be transferred out while extra_boost is active",
+            ));
+        }
+    }
+
+    let swaps_since = |picks: &FantasyTeamRound| -> Vec<(i32, i32)> {
+        let mut swaps = swapped_assets(&picks.driver_ids, &payload.driver_ids);
+        swaps.extend(swapped_assets(
+            &picks.constructor_ids,
+            &payload.constructor_ids,
+        ));
+        swaps
+    };
+
+    let free = free_transfers(previous.as_ref(), round);
+    let transfers_made = previous
+        .as_ref()
+        .map(|prev| swaps_since(prev).len() as i32)
+        .unwrap_or(0);
+    let penalty = transfer_penalty(transfers_made, free, chip.as_deref());
+
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        INSERT INTO "FantasyTeamRounds" (
+            team_id, round, driver_ids, constructor_ids, team_value,
+            free_transfers, transfers_made, penalty_points, purchase_prices
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (team_id, round)
+        DO UPDATE SET
+            driver_ids = EXCLUDED.driver_ids,
+            constructor_ids = EXCLUDED.constructor_ids,
+            team_value = EXCLUDED.team_value,
+            free_transfers = EXCLUDED.free_transfers,
+            transfers_made = EXCLUDED.transfers_made,
+            penalty_points = EXCLUDED.penalty_points,
+            purchase_prices = EXCLUDED.purchase_prices,
+            updated_at = now()
+        RETURNING *
+        "#,
+    )
+    .bind(team.id)
+    .bind(round)
+    .bind(&payload.driver_ids)
+    .bind(&payload.constructor_ids)
+    .bind(team_value)
+    .bind(free)
+    .bind(transfers_made)
+    .bind(penalty)
+    .bind(sqlx::types::Json(&prices))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Log what this edit changed compared to the last saved picks
+    if let Some(last) = current.as_ref().or(previous.as_ref()) {
+        for (asset_out, asset_in) in swaps_since(last) {
+            sqlx::query(
+                r#"
+                INSERT INTO "FantasyTransfers"
+                    (team_id, user_email, season, round, asset_out, asset_in)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(team.id)
+            .bind(&claims.sub)
+            .bind(&season)
+            .bind(round)
+            .bind(asset_out)
+            .bind(asset_in)
+            .execute(&mut *tx)
+            .await?;
+        }
+    }
+
+    ensure_unlocked(&mut *tx, &season, round).await?;
+    tx.commit().await?;
+
+    let body = team_response(&state.db_pool, &team, &picks).await?;
+    Ok((StatusCode::OK, Json(body)))
+}
+
+pub async fn get_transfers(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+    Query(params): Query<TransferQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let team = find_team(&state.db_pool, &claims.sub, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No fantasy team for this season"))?;
+
+    let rounds = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        SELECT * FROM "FantasyTeamRounds"
+        WHERE team_id = $1 AND ($2::INT IS NULL OR round = $2)
+        ORDER BY round ASC
+        "#,
+    )
+    .bind(team.id)
+    .bind(params.round)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let transfers = sqlx::query_as::<_, FantasyTransfer>(
+        r#"
+        SELECT * FROM "FantasyTransfers"
+        WHERE team_id = $1 AND ($2::INT IS NULL OR round = $2)
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(team.id)
+    .bind(params.round)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let summary: Vec<_> = rounds
+        .iter()
+        .map(|r| {
+            json!({
+                "round": r.round,
+                "free_transfers": r.free_transfers,
+                "transfers_made": r.transfers_made,
+                "penalty_points": r.penalty_points,
+            })
+        })
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "rounds": summary, "transfers": transfers })),
+    ))
+}
+
+pub async fn activate_chip(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+    Json(payload): Json<ChipPayload>,
+) -> Result<impl IntoResponse, Error> {
+    let chip = payload.chip.as_str();
+    if !CHIPS.contains(&chip) {
+        return Err(Error::new(StatusCode::BAD_REQUEST, "Unknown chip"));
+    }
+
+    let round = next_round(&state.db_pool, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "No upcoming round for this season"))?;
+
+    let team = find_team(&state.db_pool, &claims.sub, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No fantasy team for this season"))?;
+
+    let used_in: Option<i32> = sqlx::query_scalar(
+        r#"SELECT round FROM "FantasyTeamRounds" WHERE team_id = $1 AND chip = $2"#,
+    )
+    .bind(team.id)
+    .bind(chip)
+    .fetch_optional(&state.db_pool)
+    .await?;
+    if used_in.is_some_and(|r| r != round) {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "This chip has already been used this season",
+        ));
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let previous = previous_picks(&mut *tx, team.id, round).await?;
+    let current = match round_picks(&mut *tx, team.id, round).await? {
+        Some(current) => current,
+        // No changes yet this round, carry the previous picks forward
+        None => {
+            let base = previous
+                .as_ref()
+                .ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "Pick a team first"))?;
+            sqlx::query_as::<_, FantasyTeamRound>(
+                r#"
+                INSERT INTO "FantasyTeamRounds" (
+                    team_id, round, driver_ids, constructor_ids, team_value,
+                    free_transfers, purchase_prices
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                RETURNING *
+                "#,
+            )
+            .bind(team.id)
+            .bind(round)
+            .bind(&base.driver_ids)
+            .bind(&base.constructor_ids)
+            .bind(base.team_value)
+            .bind(free_transfers(Some(base), round))
+            .bind(&base.purchase_prices)
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    if current.chip.as_deref().is_some_and(|c| c != chip) {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "Another chip is already active this round",
+        ));
+    }
+    if chip == CHIP_LIMITLESS && previous.is_none() {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "Limitless needs a team from an earlier round to revert to",
+        ));
+    }
+
+    let boost_asset_id = if chip == CHIP_EXTRA_BOOST {
+        let driver = payload
+            .boost_driver_id
+            .ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "boost_driver_id is required"))?;
+        if !current.driver_ids.contains(&driver) {
+            return Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                "The boosted driver must be in your team",
+            ));
+        }
+        Some(driver)
+    } else {
+        None
+    };
+
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        UPDATE "FantasyTeamRounds"
+        SET chip = $3, boost_asset_id = $4, penalty_points = $5, updated_at = now()
+        WHERE team_id = $1 AND round = $2
+        RETURNING *
+        "#,
+    )
+    .bind(team.id)
+    .bind(round)
+    .bind(chip)
+    .bind(boost_asset_id)
+    .bind(transfer_penalty(
+        current.transfers_made,
+        current.free_transfers,
+        Some(chip),
+    ))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    ensure_unlocked(&mut *tx, &season, round).await?;
+    tx.commit().await?;
+
+    let body = team_response(&state.db_pool, &team, &picks).await?;
+    Ok((StatusCode::OK, Json(body)))
+}
+
+pub async fn cancel_chip(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let round = next_round(&state.db_pool, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::BAD_REQUEST, "No upcoming round for this season"))?;
+
+    let team = find_team(&state.db_pool, &claims.sub, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No fantasy team for this season"))?;
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let current = round_picks(&mut *tx, team.id, round)
+        .await?
+        .filter(|c| c.chip.is_some())
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No chip active this round"))?;
+
+    if current.team_value > budget_cap(None) {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "Team is over the budget cap, change it before cancelling limitless",
+        ));
+    }
+
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        UPDATE "FantasyTeamRounds"
+        SET chip = NULL, boost_asset_id = NULL, penalty_points = $3, updated_at = now()
+        WHERE team_id = $1 AND round = $2
+        RETURNING *
+        "#,
+    )
+    .bind(team.id)
+    .bind(round)
+    .bind(transfer_penalty(
+        current.transfers_made,
+        current.free_transfers,
+        None,
+    ))
+    .fetch_one(&mut *tx)
+    .await?;
+
+    ensure_unlocked(&mut *tx, &season, round).await?;
+    tx.commit().await?;
+
+    let body = team_response(&state.db_pool, &team, &picks).await?;
+    Ok((StatusCode::OK, Json(body)))
+}
+
+pub async fn get_team_history(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let team = find_team(&state.db_pool, &claims.sub, &season)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No fantasy team for this season"))?;
+
+    let history = sqlx::query_as::<_, TeamHistoryRound>(
+        r#"
+        SELECT
+            r.round,
+            r.driver_ids,
+            r.constructor_ids,
+            r.team_value,
+            r.transfers_made,
+            r.penalty_points,
+            r.chip,
+            r.boost_asset_id,
+            s.points
+        FROM "FantasyTeamRounds" r
+        LEFT JOIN "FantasyTeamScores" s ON s.team_id = r.team_id AND s.round = r.round
+        WHERE r.team_id = $1
+        ORDER BY r.round ASC
+        "#,
+    )
+    .bind(team.id)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "team": team, "history": history })),
+    ))
+}
+
+pub async fn score_fantasy_round(
+    State(state): State<Arc<AppState>>,
+    Path((season, round)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, Error> {
+    let sessions = score_round(&state, &season, round).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "season": season, "round": round, "sessions": sessions })),
+    ))
+}
+
+pub async fn get_round_points(
+    State(state): State<Arc<AppState>>,
+    Path((season, round)): Path<(String, i32)>,
+) -> Result<impl IntoResponse, Error> {
+    let points = sqlx::query_as::<_, FantasyPoints>(
+        r#"
+        SELECT season, round, session_type, asset_id, points, breakdown, rules_version, created_at
+        FROM "FantasyPoints"
+        WHERE season = $1 AND round = $2
+        ORDER BY session_type, points DESC
+        "#,
+    )
+    .bind(&season)
+    .bind(round)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": points }))))
+}
+
+pub async fn get_prices(
+    State(state): State<Arc<AppState>>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let assets = sqlx::query_as::<_, FantasyAsset>(
+        r#"
+        SELECT * FROM "FantasyAssets"
+        WHERE season = $1
+        ORDER BY asset_type DESC, price DESC
+        "#,
+    )
+    .bind(&season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let history = sqlx::query_as::<_, AssetPrice>(
+        r#"
+        SELECT asset_id, round, price, delta, rolling_points, ownership
+        FROM "FantasyAssetPrices"
+        WHERE season = $1
+        ORDER BY round ASC
+        "#,
+    )
+    .bind(&season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut by_asset: HashMap<i32, Vec<AssetPrice>> = HashMap::new();
+    for price in history {
+        by_asset.entry(price.asset_id).or_default().push(price);
+    }
+
+    let prices: Vec<AssetPriceTrend> = assets
+        .into_iter()
+        .map(|asset| {
+            let history = by_asset.remove(&asset.id).unwrap_or_default();
+            let trend = history
+                .iter()
+                .rev()
+                .take(ROLLING_ROUNDS as usize)
+                .map(|p| p.delta)
+                .sum::<f64>();
+            AssetPriceTrend {
+                asset,
+                trend: (trend * 10.0).round() / 10.0,
+                history,
+            }
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(json!({ "data": prices }))))
+}
@@ -0,0 +1,420 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use crate::{
+    models::{
+        error::Error,
+        jwt::Claims,
+        league::{
+            CreateLeaguePayload, JoinLeaguePayload, LeaderboardEntry, LeaderboardQuery, League,
+            LeagueMember, MemberRoundPoints, RenameLeaguePayload, RoundPoints,
+        },
+    },
+    utils::state::AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn invite_code() -> String {
+    Uuid::new_v4().simple().to_string()[..8].to_uppercase()
+}
+
+async fn fetch_league(db: &PgPool, league_id: i32) -> Result<League, Error> {
+    sqlx::query_as::<_, League>(r#"SELECT * FROM "Leagues" WHERE id = $1"#)
+        .bind(league_id)
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "League not found"))
+}
+
+async fn is_member(db: &PgPool, league_id: i32, user_email: &str) -> Result<bool, Error> {
+    let member: Option<i32> = sqlx::query_scalar(
+        r#"SELECT 1 FROM "LeagueMembers" WHERE league_id = $1 AND user_email = $2"#,
+    )
+    .bind(league_id)
+    .bind(user_email)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(member.is_some())
+}
+
+fn require_owner(league: &League, claims: &Claims) -> Result<(), Error> {
+    if league.owner_email != claims.sub {
+        return Err(Error::new(
+            StatusCode::FORBIDDEN,
+            "Only the league owner can do this",
+        ));
+    }
+    Ok(())
+}
+
+async fn require_visible(db: &PgPool, league: &League, claims: &Claims) -> Result<(), Error> {
+    if !league.is_public && !is_member(db, league.id, &claims.sub).await? {
+        return Err(Error::new(
+            StatusCode::FORBIDDEN,
+            "You are not a member of this league",
+        ));
+    }
+    Ok(())
+}
+
+pub async fn create_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<CreateLeaguePayload>,
+) -> Result<impl IntoResponse, Error> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "League name is required",
+        ));
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let league = sqlx::query_as::<_, League>(
+        r#"
+        INSERT INTO "Leagues" (name, season, is_public, invite_code, owner_email)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING *
+        "#,
+    )
+    .bind(name)
+    .bind(&payload.season)
+    .bind(payload.is_public)
+    .bind(invite_code())
+    .bind(&claims.sub)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query(r#"INSERT INTO "LeagueMembers" (league_id, user_email) VALUES ($1, $2)"#)
+        .bind(league.id)
+        .bind(&claims.sub)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::CREATED, Json(json!({ "data": league }))))
+}
+
+pub async fn get_my_leagues(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, Error> {
+    let leagues = sqlx::query_as::<_, League>(
+        r#"
+        SELECT l.*
+        FROM "Leagues" l
+        JOIN "LeagueMembers" m ON m.league_id = l.id
+        WHERE m.user_email = $1
+        ORDER BY l.created_at DESC
+        "#,
+    )
+    .bind(&claims.sub)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": leagues }))))
+}
+
+pub async fn get_public_leagues(
+    State(state): State<Arc<AppState>>,
+    Path(season): Path<String>,
+) -> Result<impl IntoResponse, Error> {
+    let leagues = sqlx::query_as::<_, League>(
+        r#"
+        SELECT * FROM "Leagues"
+        WHERE season = $1 AND is_public AND NOT is_closed
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(&season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": leagues }))))
+}
+
+pub async fn join_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Json(payload): Json<JoinLeaguePayload>,
+) -> Result<impl IntoResponse, Error> {
+    let league = sqlx::query_as::<_, League>(r#"SELECT * FROM "Leagues" WHERE invite_code = $1"#)
+        .bind(payload.invite_code.trim().to_uppercase())
+        .fetch_optional(&state.db_pool)
+        .await?
+        .ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "Invalid invite code"))?;
+
+    if league.is_closed {
+        return Err(Error::new(StatusCode::BAD_REQUEST, "League is closed"));
+    }
+
+    let banned: Option<i32> = sqlx::query_scalar(
+        r#"SELECT 1 FROM "LeagueBans" WHERE league_id = $1 AND user_email = $2"#,
+    )
+    .bind(league.id)
+    .bind(&claims.sub)
+    .fetch_optional(&state.db_pool)
+    .await?;
+    if banned.is_some() {
+        return Err(Error::new(
+            StatusCode::FORBIDDEN,
+            "You have been removed from this league",
+        ));
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO "LeagueMembers" (league_id, user_email)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(league.id)
+    .bind(&claims.sub)
+    .execute(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": league }))))
+}
+
+pub async fn get_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(league_id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    require_visible(&state.db_pool, &league, &claims).await?;
+
+    let mut members = sqlx::query_as::<_, LeagueMember>(
+        r#"
+        SELECT m.user_email, u.username, t.name AS team_name, m.joined_at
+        FROM "LeagueMembers" m
+        LEFT JOIN "Users" u ON LOWER(u.email) = LOWER(m.user_email)
+        LEFT JOIN "FantasyTeams" t ON t.user_email = m.user_email AND t.season = $2
+        WHERE m.league_id = $1
+        ORDER BY m.joined_at ASC
+        "#,
+    )
+    .bind(league.id)
+    .bind(&league.season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    if league.owner_email != claims.sub {
+        for member in &mut members {
+            member.user_email = None;
+        }
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({ "league": league, "members": members })),
+    ))
+}
+
+pub async fn rename_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(league_id): Path<i32>,
+    Json(payload): Json<RenameLeaguePayload>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    require_owner(&league, &claims)?;
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "League name is required",
+        ));
+    }
+
+    let league =
+        sqlx::query_as::<_, League>(r#"UPDATE "Leagues" SET name = $1 WHERE id = $2 RETURNING *"#)
+            .bind(name)
+            .bind(league.id)
+            .fetch_one(&state.db_pool)
+            .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": league }))))
+}
+
+pub async fn close_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(league_id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    require_owner(&league, &claims)?;
+
+    let league = sqlx::query_as::<_, League>(
+        r#"UPDATE "Leagues" SET is_closed = true WHERE id = $1 RETURNING *"#,
+    )
+    .bind(league.id)
+    .fetch_one(&state.db_pool)
+    .await?;
+
+    Ok((StatusCode::OK, Json(json!({ "data": league }))))
+}
+
+pub async fn leave_league(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(league_id): Path<i32>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    if league.owner_email == claims.sub {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "The league owner cannot leave, close the league instead",
+        ));
+    }
+
+    let res =
+        sqlx::query(r#"DELETE FROM "LeagueMembers" WHERE league_id = $1 AND user_email = $2"#)
+            .bind(league.id)
+            .bind(&claims.sub)
+            .execute(&state.db_pool)
+            .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::new(
+            StatusCode::NOT_FOUND,
+            "You are not a member of this league",
+        ));
+    }
+
+    Ok((StatusCode::OK, Json(json!({ "message": "Left league" }))))
+}
+
+pub async fn kick_member(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path((league_id, user_email)): Path<(i32, String)>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    require_owner(&league, &claims)?;
+
+    if user_email == league.owner_email {
+        return Err(Error::new(
+            StatusCode::BAD_REQUEST,
+            "The league owner cannot be kicked",
+        ));
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+
+    let res =
+        sqlx::query(r#"DELETE FROM "LeagueMembers" WHERE league_id = $1 AND user_email = $2"#)
+            .bind(league.id)
+            .bind(&user_email)
+            .execute(&mut *tx)
+            .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(Error::new(StatusCode::NOT_FOUND, "Member not found"));
+    }
+
+    // Keeps the member from joining again with the same invite code
+    sqlx::query(
+        r#"
+        INSERT INTO "LeagueBans" (league_id, user_email)
+        VALUES ($1, $2)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(league.id)
+    .bind(&user_email)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, Json(json!({ "message": "Member removed" }))))
+}
+
+pub async fn get_leaderboard(
+    State(state): State<Arc<AppState>>,
+    Extension(claims): Extension<Claims>,
+    Path(league_id): Path<i32>,
+    Query(params): Query<LeaderboardQuery>,
+) -> Result<impl IntoResponse, Error> {
+    let league = fetch_league(&state.db_pool, league_id).await?;
+    require_visible(&state.db_pool, &league, &claims).await?;
+
+    let rows = sqlx::query_as::<_, MemberRoundPoints>(
+        r#"
+        SELECT m.user_email, u.username, t.name AS team_name, s.round, s.points
+        FROM "LeagueMembers" m
+        LEFT JOIN "Users" u ON LOWER(u.email) = LOWER(m.user_email)
+        LEFT JOIN "FantasyTeams" t ON t.user_email = m.user_email AND t.season = $2
+        LEFT JOIN "FantasyTeamScores" s ON s.team_id = t.id
+        WHERE m.league_id = $1
+        ORDER BY s.round ASC
+        "#,
+    )
+    .bind(league.id)
+    .bind(&league.season)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut by_member: BTreeMap<String, LeaderboardEntry> = BTreeMap::new();
+    for row in rows {
+        let entry = by_member
+            .entry(row.user_email.clone())
+            .or_insert_with(|| LeaderboardEntry {
+                rank: 0,
+                username: row.username.clone(),
+                team_name: row.team_name.clone(),
+                points: 0.0,
+                season_points: 0.0,
+                rounds: Vec::new(),
+            });
+
+        if let (Some(round), Some(points)) = (row.round, row.points) {
+            entry.season_points += points;
+            entry.rounds.push(RoundPoints { round, points });
+        }
+    }
+
+    let mut standings: Vec<LeaderboardEntry> = by_member
+        .into_values()
+        .map(|mut entry| {
+            entry.points = match params.round {
+                Some(round) => entry
+                    .rounds
+                    .iter()
+                    .find(|r| r.round == round)
+                    .map(|r| r.points)
+                    .unwrap_or(0.0),
+                None => entry.season_points,
+            };
+            entry
+        })
+        .collect();
+
+    standings.sort_by(|a, b| b.points.total_cmp(&a.points));
+    for (i, entry) in standings.iter_mut().enumerate() {
+        entry.rank = (i + 1) as u32;
+    }
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "league": league,
+            "round": params.round,
+            "standings": standings,
+        })),
+    ))
+}
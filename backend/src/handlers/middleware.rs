@@ -38,3 +38,28 @@ pub async fn auth_middleware(
 
     Ok(next.run(req).await)
 }
+
+/// Lets only users flagged `is_admin` in "Users" through. Must run after
+/// `auth_middleware`, which provides the claims.
+pub async fn admin_middleware(
+    State(state): State<Arc<AppState>>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, Error> {
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or_else(|| Error::new(StatusCode::UNAUTHORIZED, "Missing authorization header"))?;
+
+    let is_admin: Option<bool> =
+        sqlx::query_scalar(r#"SELECT is_admin FROM "Users" WHERE email = $1"#)
+            .bind(&claims.sub)
+            .fetch_optional(&state.db_pool)
+            .await?;
+
+    if !is_admin.unwrap_or(false) {
+        return Err(Error::new(StatusCode::FORBIDDEN, "Admin access required"));
+    }
+
+    Ok(next.run(req).await)
+}
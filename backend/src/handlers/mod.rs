@@ -5,4 +5,6 @@ pub mod race;
 pub mod session;
 pub mod standings;
 pub mod weather;
-pub mod news;
\ No newline at end of file
+pub mod news;
+pub mod fantasy;
+pub mod league;
\ No newline at end of file
@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
-use crate::utils::state::AppState;
+use crate::{
+    models::error::Error,
+    services::archive::{self, is_archived},
+    utils::state::AppState,
+};
 use axum::{
     extract::{Query, State},
     response::IntoResponse,
     Json,
 };
 use http::StatusCode;
-use serde_json::{from_str, Value};
 
 use serde::Deserialize;
 
@@ -20,22 +23,15 @@ pub struct WeatherQuery {
 pub async fn get_weather(
     State(state): State<Arc<AppState>>,
     Query(params): Query<WeatherQuery>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, Error> {
     let session_key = params.session_key.unwrap_or_else(|| "latest".to_string());
     let meeting_key = params.meeting_key.unwrap_or_else(|| "latest".to_string());
-    
-    let res = state
-        .http_client
-        .get(format!(
-            "https://api.openf1.org/v1/weather?meeting_key={meeting_key}&session_key={session_key}"
-        ))
-        .send()
-        .await
-        .unwrap();
 
-    let body = res.text().await.unwrap();
-    let res: Value = from_str(&body).unwrap();
-    let res_body = &res;
+    // Archived sessions are served locally, "latest" always goes to OpenF1
+    let weather = match session_key.parse::<u32>() {
+        Ok(key) if is_archived(&state.db_pool, key).await? => archive::weather(&state, key).await?,
+        _ => state.openf1.weather(&meeting_key, &session_key).await?,
+    };
 
-    (StatusCode::OK, Json(res_body)).into_response()
+    Ok((StatusCode::OK, Json(weather)))
 }
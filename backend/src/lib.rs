@@ -0,0 +1,6 @@
+pub mod clients;
+pub mod handlers;
+pub mod models;
+pub mod routes;
+pub mod services;
+pub mod utils;
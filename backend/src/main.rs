@@ -1,9 +1,5 @@
-mod handlers;
-mod models;
-mod routes;
-mod utils;
-use crate::routes::make_app;
 use axum::serve;
+use backend::routes::make_app;
 use std::error::Error;
 use tokio::net::TcpListener;
 
@@ -4,13 +4,16 @@ use chrono::{DateTime, Utc, Duration};
 pub struct CacheEntry<T> {
     pub value: T,
     pub expires_at: DateTime<Utc>,
+    // Tick of the last read or write, the lowest one is evicted first
+    pub last_used: u64,
 }
 
 impl<T> CacheEntry<T> {
-    pub fn new(value: T, ttl_seconds: i64) -> Self {
+    pub fn new(value: T, ttl_seconds: i64, tick: u64) -> Self {
         Self {
             value,
             expires_at: Utc::now() + Duration::seconds(ttl_seconds),
+            last_used: tick,
         }
     }
 
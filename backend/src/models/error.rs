@@ -5,6 +5,8 @@ use axum::Json;
 use serde_json::json;
 use serde_json::Value;
 
+use crate::clients::{jolpica::JolpicaError, openf1::OpenF1Error};
+
 
 #[derive(Debug)]
 pub struct Error {
@@ -21,6 +23,17 @@ impl Error {
     }
 }
 
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.body["message"].as_str() {
+            Some(message) => write!(f, "{} ({})", message, self.code),
+            None => write!(f, "{} ({})", self.body.0, self.code),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
         (self.code, self.body).into_response()
@@ -43,4 +56,57 @@ impl From<argon2::password_hash::errors::Error> for Error {
     fn from(error: argon2::password_hash::errors::Error) -> Self {
         Self::new(StatusCode::INTERNAL_SERVER_ERROR, &error.to_string())
     }
-}
\ No newline at end of file
+}
+impl From<sqlx::Error> for Error {
+    fn from(error: sqlx::Error) -> Self {
+        tracing::error!("Database error: {:?}", error);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "Database error")
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(error: reqwest::Error) -> Self {
+        tracing::error!("Upstream request failed: {:?}", error);
+        Self::new(StatusCode::BAD_GATEWAY, "Upstream request failed")
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error: std::io::Error) -> Self {
+        tracing::error!("I/O error: {:?}", error);
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, "I/O error")
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Self {
+        tracing::error!("Unexpected upstream payload: {:?}", error);
+        Self::new(StatusCode::BAD_GATEWAY, "Unexpected upstream payload")
+    }
+}
+
+impl From<OpenF1Error> for Error {
+    fn from(error: OpenF1Error) -> Self {
+        tracing::error!("{}", error);
+        match error {
+            OpenF1Error::Status(StatusCode::TOO_MANY_REQUESTS, _) => Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "OpenF1 API rate limit reached",
+            ),
+            _ => Self::new(StatusCode::BAD_GATEWAY, "OpenF1 request failed"),
+        }
+    }
+}
+
+impl From<JolpicaError> for Error {
+    fn from(error: JolpicaError) -> Self {
+        tracing::error!("{}", error);
+        match error {
+            JolpicaError::Status(StatusCode::TOO_MANY_REQUESTS, _) => Self::new(
+                StatusCode::TOO_MANY_REQUESTS,
+                "Jolpica API rate limit reached",
+            ),
+            _ => Self::new(StatusCode::BAD_GATEWAY, "Jolpica request failed"),
+        }
+    }
+}
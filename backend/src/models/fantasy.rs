@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+
+pub const ASSET_DRIVER: &str = "driver";
+pub const ASSET_CONSTRUCTOR: &str = "constructor";
+
+pub const CHIP_WILDCARD: &str = "wildcard";
+pub const CHIP_LIMITLESS: &str = "limitless";
+pub const CHIP_EXTRA_BOOST: &str = "extra_boost";
+pub const CHIPS: [&str; 3] = [CHIP_WILDCARD, CHIP_LIMITLESS, CHIP_EXTRA_BOOST];
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct FantasyAsset {
+    pub id: i32,
+    pub season: String,
+    pub asset_type: String,
+    // Driver number for drivers, Ergast constructorId for constructors
+    pub external_ref: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub constructor_ref: Option<String>,
+    pub price: f64,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct FantasyTeam {
+    pub id: i32,
+    pub user_email: String,
+    pub season: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct FantasyTeamRound {
+    pub team_id: i32,
+    pub round: i32,
+    pub driver_ids: Vec<i32>,
+    pub constructor_ids: Vec<i32>,
+    pub team_value: f64,
+    pub free_transfers: i32,
+    pub transfers_made: i32,
+    pub penalty_points: f64,
+    pub chip: Option<String>,
+    pub boost_asset_id: Option<i32>,
+    // Price every pick was bought at, by asset id
+    pub purchase_prices: Json<HashMap<i32, f64>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct FantasyTransfer {
+    pub id: i32,
+    pub team_id: i32,
+    pub user_email: String,
+    pub season: String,
+    pub round: i32,
+    pub asset_out: i32,
+    pub asset_in: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TransferQuery {
+    pub round: Option<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FantasyTeamPayload {
+    pub name: String,
+    pub driver_ids: Vec<i32>,
+    pub constructor_ids: Vec<i32>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChipPayload {
+    pub chip: String,
+    pub boost_driver_id: Option<i32>,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize)]
+pub struct TeamHistoryRound {
+    pub round: i32,
+    pub driver_ids: Vec<i32>,
+    pub constructor_ids: Vec<i32>,
+    pub team_value: f64,
+    pub transfers_made: i32,
+    pub penalty_points: f64,
+    pub chip: Option<String>,
+    pub boost_asset_id: Option<i32>,
+    pub points: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LockStatus {
+    pub season: String,
+    pub round: i32,
+    pub lock_time: Option<DateTime<Utc>>,
+    pub locked: bool,
+    pub seconds_until_lock: Option<i64>,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize)]
+pub struct AssetPrice {
+    pub asset_id: i32,
+    pub round: i32,
+    pub price: f64,
+    pub delta: f64,
+    pub rolling_points: f64,
+    pub ownership: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AssetPriceTrend {
+    pub asset: FantasyAsset,
+    // Sum of the price changes over the last few rounds
+    pub trend: f64,
+    pub history: Vec<AssetPrice>,
+}
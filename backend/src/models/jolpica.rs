@@ -0,0 +1,229 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::race_utils::parse_jolpica_time;
+
+// Payloads of the Jolpica (Ergast compatible) API, see https://api.jolpi.ca.
+// Field names are kept as Ergast sends them since some are passed through to
+// clients unchanged.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub lat: Option<String>,
+    pub long: Option<String>,
+    pub locality: Option<String>,
+    pub country: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Circuit {
+    pub circuit_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub circuit_name: String,
+    #[serde(rename = "Location")]
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTime {
+    pub date: NaiveDate,
+    // Jolpica times look like "14:00:00Z"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+}
+
+impl SessionTime {
+    /// Scheduled start in UTC, midnight when Jolpica has no time.
+    pub fn start(&self) -> NaiveDateTime {
+        self.date
+            .and_time(parse_jolpica_time(self.time.as_deref()).unwrap_or(NaiveTime::MIN))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Driver {
+    pub driver_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permanent_number: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub given_name: String,
+    pub family_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_of_birth: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nationality: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Constructor {
+    pub constructor_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nationality: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResultTime {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub millis: Option<String>,
+    pub time: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AverageSpeed {
+    pub units: String,
+    pub speed: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FastestLap {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<String>,
+    pub lap: String,
+    #[serde(rename = "Time", skip_serializing_if = "Option::is_none")]
+    pub time: Option<ResultTime>,
+    #[serde(rename = "AverageSpeed", skip_serializing_if = "Option::is_none")]
+    pub average_speed: Option<AverageSpeed>,
+}
+
+/// Race or sprint classification entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RaceResult {
+    pub number: String,
+    pub position: String,
+    pub position_text: String,
+    pub points: String,
+    #[serde(rename = "Driver")]
+    pub driver: Driver,
+    #[serde(rename = "Constructor")]
+    pub constructor: Constructor,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub laps: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(rename = "Time", skip_serializing_if = "Option::is_none")]
+    pub time: Option<ResultTime>,
+    #[serde(rename = "FastestLap", skip_serializing_if = "Option::is_none")]
+    pub fastest_lap: Option<FastestLap>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualifyingResult {
+    pub number: String,
+    pub position: String,
+    #[serde(rename = "Driver")]
+    pub driver: Driver,
+    #[serde(rename = "Constructor")]
+    pub constructor: Constructor,
+    #[serde(rename = "Q1", skip_serializing_if = "Option::is_none")]
+    pub q1: Option<String>,
+    #[serde(rename = "Q2", skip_serializing_if = "Option::is_none")]
+    pub q2: Option<String>,
+    #[serde(rename = "Q3", skip_serializing_if = "Option::is_none")]
+    pub q3: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct Race {
+    #[serde(rename = "season")]
+    pub season: String,
+    #[serde(rename = "round")]
+    pub round: String,
+    #[serde(rename = "url", skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(rename = "raceName")]
+    pub race_name: String,
+    pub circuit: Circuit,
+    #[serde(rename = "date")]
+    pub date: NaiveDate,
+    #[serde(rename = "time", skip_serializing_if = "Option::is_none")]
+    pub time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_practice: Option<SessionTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second_practice: Option<SessionTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub third_practice: Option<SessionTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub qualifying: Option<SessionTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sprint: Option<SessionTime>,
+    // Called SprintShootout in 2023
+    #[serde(alias = "SprintShootout", skip_serializing_if = "Option::is_none")]
+    pub sprint_qualifying: Option<SessionTime>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<RaceResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sprint_results: Vec<RaceResult>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub qualifying_results: Vec<QualifyingResult>,
+}
+
+impl Race {
+    /// Scheduled race start in UTC, midnight when Jolpica has no time.
+    pub fn start(&self) -> NaiveDateTime {
+        self.date
+            .and_time(parse_jolpica_time(self.time.as_deref()).unwrap_or(NaiveTime::MIN))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceTable {
+    #[serde(rename = "Races", default)]
+    pub races: Vec<Race>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DriverStanding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    pub position_text: String,
+    pub points: String,
+    pub wins: String,
+    #[serde(rename = "Driver")]
+    pub driver: Driver,
+    #[serde(rename = "Constructors", default)]
+    pub constructors: Vec<Constructor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConstructorStanding {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    pub position_text: String,
+    pub points: String,
+    pub wins: String,
+    #[serde(rename = "Constructor")]
+    pub constructor: Constructor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandingsList {
+    pub season: String,
+    pub round: String,
+    #[serde(rename = "DriverStandings", default)]
+    pub driver_standings: Vec<DriverStanding>,
+    #[serde(rename = "ConstructorStandings", default)]
+    pub constructor_standings: Vec<ConstructorStanding>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StandingsTable {
+    #[serde(rename = "StandingsLists", default)]
+    pub standings_lists: Vec<StandingsList>,
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct League {
+    pub id: i32,
+    pub name: String,
+    pub season: String,
+    pub is_public: bool,
+    pub invite_code: String,
+    pub owner_email: String,
+    pub is_closed: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct LeagueMember {
+    // Only shown to the league owner
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_email: Option<String>,
+    pub username: Option<String>,
+    pub team_name: Option<String>,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateLeaguePayload {
+    pub name: String,
+    pub season: String,
+    #[serde(default)]
+    pub is_public: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct JoinLeaguePayload {
+    pub invite_code: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RenameLeaguePayload {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LeaderboardQuery {
+    pub round: Option<i32>,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct MemberRoundPoints {
+    pub user_email: String,
+    pub username: Option<String>,
+    pub team_name: Option<String>,
+    pub round: Option<i32>,
+    pub points: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RoundPoints {
+    pub round: i32,
+    pub points: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub username: Option<String>,
+    pub team_name: Option<String>,
+    pub points: f64,
+    pub season_points: f64,
+    pub rounds: Vec<RoundPoints>,
+}
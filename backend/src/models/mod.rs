@@ -5,4 +5,10 @@ pub mod telemetry;
 pub mod cache;
 pub mod news;
 pub mod session;
-pub mod race;
\ No newline at end of file
+pub mod race;
+pub mod fantasy;
+pub mod scoring;
+pub mod league;
+pub mod jolpica;
+pub mod openf1;
+pub mod strategy;
\ No newline at end of file
@@ -0,0 +1,153 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Payloads of the OpenF1 endpoints, see https://openf1.org
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lap {
+    pub session_key: u32,
+    pub driver_number: u32,
+    pub lap_number: u32,
+    pub date_start: Option<DateTime<Utc>>,
+    pub lap_duration: Option<f64>,
+    pub duration_sector_1: Option<f64>,
+    pub duration_sector_2: Option<f64>,
+    pub duration_sector_3: Option<f64>,
+    pub i1_speed: Option<f64>,
+    pub i2_speed: Option<f64>,
+    pub st_speed: Option<f64>,
+    pub is_pit_out_lap: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CarData {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub speed: f64,
+    pub throttle: Option<f64>,
+    pub brake: Option<f64>,
+    pub n_gear: Option<u32>,
+    pub rpm: Option<u32>,
+    pub drs: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Location {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Position {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub position: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResult {
+    pub session_key: u32,
+    pub meeting_key: u32,
+    pub driver_number: u32,
+    pub position: Option<u32>,
+    pub number_of_laps: Option<u32>,
+    pub points: Option<f64>,
+    pub dnf: Option<bool>,
+    pub dns: Option<bool>,
+    pub dsq: Option<bool>,
+    // A number for races, [Q1, Q2, Q3] for qualifying
+    pub duration: Option<Value>,
+    pub gap_to_leader: Option<Value>,
+}
+
+impl SessionResult {
+    /// Q1, Q2 and Q3 times of a qualifying result.
+    pub fn segment_durations(&self) -> Vec<Option<f64>> {
+        match &self.duration {
+            Some(Value::Array(segments)) => segments.iter().map(|d| d.as_f64()).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub session_key: i32,
+    pub meeting_key: i32,
+    pub session_name: String,
+    pub session_type: Option<String>,
+    pub date_start: DateTime<Utc>,
+    pub date_end: Option<DateTime<Utc>>,
+    pub year: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Weather {
+    pub session_key: u32,
+    pub meeting_key: u32,
+    pub date: DateTime<Utc>,
+    pub air_temperature: Option<f64>,
+    pub track_temperature: Option<f64>,
+    pub humidity: Option<f64>,
+    pub pressure: Option<f64>,
+    pub rainfall: Option<f64>,
+    pub wind_direction: Option<f64>,
+    pub wind_speed: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionDriver {
+    pub driver_number: u32,
+    pub name_acronym: Option<String>,
+    pub full_name: Option<String>,
+    pub team_name: Option<String>,
+    pub team_colour: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stint {
+    pub driver_number: u32,
+    pub stint_number: u32,
+    pub compound: Option<String>,
+    pub lap_start: Option<u32>,
+    pub lap_end: Option<u32>,
+    pub tyre_age_at_start: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PitStop {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    pub lap_number: u32,
+    // Time from pit entry to pit exit
+    pub pit_duration: Option<f64>,
+    // Stationary time, only reported from 2024 on
+    pub stop_duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interval {
+    pub date: DateTime<Utc>,
+    pub driver_number: u32,
+    // Seconds, or a string like "+1 LAP"
+    pub gap_to_leader: Option<Value>,
+    pub interval: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RaceControlMessage {
+    pub date: DateTime<Utc>,
+    pub category: Option<String>,
+    pub flag: Option<String>,
+    pub scope: Option<String>,
+    pub sector: Option<u32>,
+    pub driver_number: Option<u32>,
+    pub lap_number: Option<u32>,
+    pub message: String,
+    // 1-3 during qualifying sessions
+    pub qualifying_phase: Option<u32>,
+}
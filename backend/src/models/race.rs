@@ -3,7 +3,7 @@ use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
 #[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
-pub struct _Race {
+pub struct Race {
     pub id: i64,
     pub created_at: Option<chrono::DateTime<chrono::Utc>>,
     pub season: String,
@@ -46,3 +46,12 @@ pub struct RaceWithCircuit {
     pub lat: Option<String>,
     pub long: Option<String>,
 }
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CalendarSyncReport {
+    pub season: String,
+    pub circuits_changed: usize,
+    pub races_changed: usize,
+    pub sessions_changed: usize,
+    pub changes: Vec<String>,
+}
@@ -0,0 +1,73 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringRules {
+    // Points for P1, P2, ... Positions outside the list score nothing
+    pub position_points: Vec<f64>,
+    pub position_gained: f64,
+    pub position_lost: f64,
+    pub fastest_lap: f64,
+    pub dnf: f64,
+    pub beat_teammate: f64,
+}
+
+#[derive(FromRow, Debug, Clone)]
+pub struct ScoringRuleSet {
+    pub version: i32,
+    pub rules: Json<ScoringRules>,
+}
+
+/// One classified (or retired) driver of a session, as needed for scoring.
+#[derive(Debug, Clone)]
+pub struct ScoringInput {
+    pub driver_number: String,
+    pub constructor_ref: String,
+    pub position: u32,
+    pub grid: Option<u32>,
+    pub classified: bool,
+    pub fastest_lap: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PointsBreakdown {
+    pub position: f64,
+    pub positions_gained: f64,
+    pub fastest_lap: f64,
+    pub dnf: f64,
+    pub beat_teammate: f64,
+}
+
+impl PointsBreakdown {
+    pub fn total(&self) -> f64 {
+        self.position + self.positions_gained + self.fastest_lap + self.dnf + self.beat_teammate
+    }
+
+    pub fn add(&mut self, other: &PointsBreakdown) {
+        self.position += other.position;
+        self.positions_gained += other.positions_gained;
+        self.fastest_lap += other.fastest_lap;
+        self.dnf += other.dnf;
+        self.beat_teammate += other.beat_teammate;
+    }
+}
+
+#[derive(FromRow, Debug, Clone, Serialize, Deserialize)]
+pub struct FantasyPoints {
+    pub season: String,
+    pub round: i32,
+    pub session_type: String,
+    pub asset_id: i32,
+    pub points: f64,
+    pub breakdown: Json<PointsBreakdown>,
+    pub rules_version: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionScore {
+    pub session_type: String,
+    pub rules_version: i32,
+    pub assets_scored: usize,
+}
@@ -1,4 +1,4 @@
-use chrono::{NaiveDate, NaiveTime};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
@@ -16,3 +16,39 @@ pub struct Session {
     pub session_key: Option<i32>,
     pub meeting_key: Option<i32>,
 }
+
+/// Session that has started but still has no session_key.
+#[derive(FromRow, Debug, Clone)]
+pub struct PendingSession {
+    pub id: i32,
+    pub race_id: i64,
+    pub season: String,
+    pub session_type: String,
+    pub start: NaiveDateTime,
+}
+
+#[derive(FromRow, Debug, Clone, Serialize)]
+pub struct UnresolvedSession {
+    pub session_id: i32,
+    pub race_id: i64,
+    pub session_type: String,
+    pub reason: String,
+    pub attempts: i32,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_attempt_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SessionKeyReport {
+    pub resolved: usize,
+    pub unresolved: usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveReport {
+    pub session_key: u32,
+    // Already archived, nothing was downloaded
+    pub skipped: bool,
+    pub drivers: usize,
+    pub rows: usize,
+}
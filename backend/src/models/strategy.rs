@@ -0,0 +1,119 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StintSummary {
+    pub stint_number: u32,
+    pub compound: Option<String>,
+    pub lap_start: Option<u32>,
+    pub lap_end: Option<u32>,
+    pub laps: Option<u32>,
+    pub tyre_age_at_start: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DriverStints {
+    pub driver_number: u32,
+    pub name_acronym: Option<String>,
+    pub team_name: Option<String>,
+    pub stints: Vec<StintSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DriverStrategy {
+    pub driver_number: u32,
+    pub stops: u32,
+    // Compounds in the order they were used, e.g. ["MEDIUM", "HARD"]
+    pub compounds: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TeamStrategy {
+    pub team_name: String,
+    pub drivers: Vec<DriverStrategy>,
+    // The team's drivers ran different compound sequences
+    pub split_strategy: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionStints {
+    pub session_key: u32,
+    pub drivers: Vec<DriverStints>,
+    pub teams: Vec<TeamStrategy>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PitStopSummary {
+    pub driver_number: u32,
+    pub name_acronym: Option<String>,
+    pub team_name: Option<String>,
+    pub lap_number: u32,
+    pub date: DateTime<Utc>,
+    // Pit entry to pit exit
+    pub lane_duration: Option<f64>,
+    pub stop_duration: Option<f64>,
+    // Seconds slower than the field median, negative when quicker
+    pub delta_to_median: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapKind {
+    // The driver who stopped first came out ahead
+    Undercut,
+    // The driver who stopped later came out ahead
+    Overcut,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PitSwap {
+    pub kind: SwapKind,
+    pub gained_by: u32,
+    pub lost_by: u32,
+    pub gained_by_lap: u32,
+    pub lost_by_lap: u32,
+    // Positions of the driver who gained
+    pub position_before: u32,
+    pub position_after: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PitAnalysis {
+    pub session_key: u32,
+    pub median_lane_duration: Option<f64>,
+    pub stops: Vec<PitStopSummary>,
+    // Quickest stop of every team, quickest team first
+    pub fastest_by_team: Vec<PitStopSummary>,
+    pub swaps: Vec<PitSwap>,
+}
+
+#[derive(Deserialize)]
+pub struct LongRunQuery {
+    // Add back the time gained from burning fuel before fitting degradation
+    pub fuel_corrected: Option<bool>,
+}
+
+/// Long-run pace of a driver on one compound over the practice sessions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LongRunPace {
+    // Rank among the runs on the same compound
+    pub rank: u32,
+    pub driver_number: u32,
+    pub name_acronym: Option<String>,
+    pub team_name: Option<String>,
+    pub compound: String,
+    pub runs: u32,
+    pub laps: u32,
+    pub average_lap_time: f64,
+    // Seconds lost per lap of tyre age
+    pub degradation_per_lap: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LongRunTable {
+    pub season: String,
+    pub round: i32,
+    pub fuel_corrected: bool,
+    pub session_keys: Vec<i32>,
+    pub pace: Vec<LongRunPace>,
+}
@@ -1,57 +1,25 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-#[allow(dead_code)]
-#[derive(Deserialize)]
-pub struct CarDataPoint {
-    pub date: String,
-    pub session_key: u32,
-    pub driver_number: u32,
-    pub throttle: Option<f64>,
-    pub meeting_key: u32,
-    pub brake: Option<f64>,
-    pub n_gear: Option<u32>,
-    pub rpm: Option<u32>,
-    pub speed: f64,
-    pub drs: Option<u32>,
-}
-
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct SpeedDistance {
     pub speed: f64,
     pub distance: f64,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct LapRecord {
-    pub lap_number: u32,
-
-    #[serde(rename = "date_start")]
-    pub date_start: Option<DateTime<Utc>>,
-
-    pub driver_number: u32,
-}
-
-#[derive(Debug, Deserialize, Clone)]
-pub struct PositionRecord {
-    pub position: u32,
-    pub driver_number: u32,
-    pub date: DateTime<Utc>,
-}
-
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LapPosition {
     pub lap: u32,
     pub position: u32,
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DriverLapGraph {
     pub driver_number: u32,
     pub data: Vec<LapPosition>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct FastestLapSector {
     pub position: u32,
     pub driver_number: u32,
@@ -65,23 +33,10 @@ pub struct FastestLapSector {
 pub struct PaceQuery {
     pub driver_1: u32,
     pub driver_2: u32,
+    pub minisectors: Option<u32>,
 }
 
-#[derive(Deserialize)]
-pub struct LocationPoint {
-    pub date: String,
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
-
-#[derive(Deserialize)]
-pub struct Lap {
-    pub lap_duration: Option<f64>,
-    pub date_start: Option<String>,
-}
-
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct PacePoint {
     pub x: f64,
     pub y: f64,
@@ -111,3 +66,188 @@ pub struct QualifyingRankings {
     pub q2: Vec<QualifyingRanking>,
     pub q3: Vec<QualifyingRanking>,
 }
+
+/// A car_data sample placed on the lap.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TracePoint {
+    pub date: DateTime<Utc>,
+    // Metres since the start of the lap
+    pub distance: f64,
+    pub x: f64,
+    pub y: f64,
+    pub speed: f64,
+    pub throttle: Option<f64>,
+    pub brake: Option<f64>,
+    pub n_gear: Option<u32>,
+    pub rpm: Option<u32>,
+    pub drs: Option<u32>,
+}
+
+/// Which lap of a driver to build telemetry from. At most one option can be
+/// set; without any the latest representative lap is used.
+#[derive(Deserialize)]
+pub struct LapQuery {
+    pub lap: Option<u32>,
+    pub fastest: Option<bool>,
+    // q1, q2 or q3, picks the fastest lap of that segment
+    pub segment: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LapInfo {
+    pub lap_number: u32,
+    pub date_start: DateTime<Utc>,
+    pub lap_duration: f64,
+    pub duration_sector_1: Option<f64>,
+    pub duration_sector_2: Option<f64>,
+    pub duration_sector_3: Option<f64>,
+    pub is_pit_out_lap: bool,
+    pub segment: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LapTrace {
+    pub lap: LapInfo,
+    pub points: Vec<TracePoint>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    Speed,
+    Throttle,
+    Brake,
+    NGear,
+    Rpm,
+    Drs,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 6] = [
+        Channel::Speed,
+        Channel::Throttle,
+        Channel::Brake,
+        Channel::NGear,
+        Channel::Rpm,
+        Channel::Drs,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim() {
+            "speed" => Some(Channel::Speed),
+            "throttle" => Some(Channel::Throttle),
+            "brake" => Some(Channel::Brake),
+            "n_gear" | "gear" => Some(Channel::NGear),
+            "rpm" => Some(Channel::Rpm),
+            "drs" => Some(Channel::Drs),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TelemetryQuery {
+    // Comma separated, all channels when missing
+    pub channels: Option<String>,
+    // Keep at most one sample per this many metres
+    pub step: Option<f64>,
+}
+
+/// One row of a telemetry trace, holding only the requested channels.
+#[derive(Debug, Serialize, Default)]
+pub struct TelemetrySample {
+    pub distance: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub throttle: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brake: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n_gear: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rpm: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drs: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DriverTelemetry {
+    pub session_key: u32,
+    pub driver_number: u32,
+    pub lap: LapInfo,
+    pub channels: Vec<Channel>,
+    pub samples: Vec<TelemetrySample>,
+}
+
+#[derive(Deserialize)]
+pub struct DeltaQuery {
+    pub driver_1: u32,
+    pub driver_2: u32,
+    // Lap numbers, the fastest lap when missing
+    pub lap_1: Option<u32>,
+    pub lap_2: Option<u32>,
+    // Compare the fastest laps of a quali segment instead
+    pub segment: Option<String>,
+    // Metres between two delta points
+    pub step: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeltaPoint {
+    pub distance: f64,
+    // Seconds driver_2 is behind driver_1, negative when ahead
+    pub delta: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LapDelta {
+    pub session_key: u32,
+    pub driver_1: u32,
+    pub driver_2: u32,
+    pub lap_1: LapInfo,
+    pub lap_2: LapInfo,
+    pub points: Vec<DeltaPoint>,
+}
+
+#[derive(Deserialize)]
+pub struct MinisectorQuery {
+    // Comma separated driver numbers
+    pub drivers: String,
+    pub minisectors: Option<u32>,
+    // Compare the fastest laps of a quali segment instead of the session
+    pub segment: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinisectorTime {
+    pub driver_number: u32,
+    pub time: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Minisector {
+    pub minisector: u32,
+    // Metres along the lap of the first driver
+    pub start_distance: f64,
+    pub end_distance: f64,
+    pub x: f64,
+    pub y: f64,
+    pub fastest_driver: u32,
+    // Seconds the fastest driver gained on the next fastest
+    pub time_gained: f64,
+    pub times: Vec<MinisectorTime>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DriverLap {
+    pub driver_number: u32,
+    pub lap: LapInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MinisectorComparison {
+    pub session_key: u32,
+    pub laps: Vec<DriverLap>,
+    pub minisectors: Vec<Minisector>,
+}
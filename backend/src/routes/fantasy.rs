@@ -0,0 +1,43 @@
+use crate::{
+    handlers::{
+        fantasy::{
+            activate_chip, cancel_chip, get_assets, get_prices, get_round_points, get_team,
+            get_team_history, get_transfers, save_team, score_fantasy_round,
+        },
+        middleware::{admin_middleware, auth_middleware},
+    },
+    utils::state::AppState,
+};
+use axum::{
+    extract::State,
+    middleware::from_fn,
+    routing::{get, post},
+    Router,
+};
+use std::sync::Arc;
+
+pub fn fantasy_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let fantasy_router = Router::new()
+        .route("/assets/{season}", get(get_assets))
+        .route("/team/{season}", get(get_team).put(save_team))
+        .route("/team/{season}/history", get(get_team_history))
+        .route("/chips/{season}", post(activate_chip).delete(cancel_chip))
+        .route("/transfers/{season}", get(get_transfers))
+        .route("/prices/{season}", get(get_prices))
+        .route("/points/{season}/{round}", get(get_round_points))
+        .with_state(state.clone());
+
+    // Scoring also moves asset prices, so only admins may trigger it
+    let admin_state = state.clone();
+    let admin_router = Router::new()
+        .route("/score/{season}/{round}", post(score_fantasy_round))
+        .route_layer(from_fn(move |req, next| {
+            admin_middleware(State(admin_state.clone()), req, next)
+        }))
+        .with_state(state.clone());
+    let fantasy_router = fantasy_router.merge(admin_router);
+
+    fantasy_router.layer(from_fn(move |req, next| {
+        auth_middleware(State(state.clone()), req, next)
+    }))
+}
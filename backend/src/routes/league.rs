@@ -0,0 +1,34 @@
+use crate::{
+    handlers::{
+        league::{
+            close_league, create_league, get_leaderboard, get_league, get_my_leagues,
+            get_public_leagues, join_league, kick_member, leave_league, rename_league,
+        },
+        middleware::auth_middleware,
+    },
+    utils::state::AppState,
+};
+use axum::{
+    extract::State,
+    middleware::from_fn,
+    routing::{delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+
+pub fn league_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
+    let league_router = Router::new()
+        .route("/", get(get_my_leagues).post(create_league))
+        .route("/public/{season}", get(get_public_leagues))
+        .route("/join", post(join_league))
+        .route("/{id}", get(get_league).patch(rename_league))
+        .route("/{id}/close", post(close_league))
+        .route("/{id}/leave", post(leave_league))
+        .route("/{id}/members/{user_email}", delete(kick_member))
+        .route("/{id}/leaderboard", get(get_leaderboard))
+        .with_state(state.clone());
+
+    league_router.layer(from_fn(move |req, next| {
+        auth_middleware(State(state.clone()), req, next)
+    }))
+}
@@ -1,9 +1,10 @@
+pub mod fantasy;
+pub mod league;
 pub mod race;
 pub mod session;
 pub mod standings;
 pub mod users;
 use axum::{middleware::from_fn, response::IntoResponse, routing::get, Json, Router};
-use dashmap::DashMap;
 use http::StatusCode;
 use serde_json::json;
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
@@ -16,18 +17,23 @@ pub mod auth;
 pub use auth::auth_routes;
 
 use crate::{
+    clients::{jolpica::JolpicaClient, openf1::OpenF1Client},
     handlers::{middleware::auth_middleware, news::get_news, weather::get_weather},
-    models::{
-        cache::CacheEntry,
-        telemetry::{
-            DriverLapGraph, FastestLapSector, PacePoint, QualifyingRankings, SpeedDistance,
-        },
+    routes::{
+        fantasy::fantasy_routes, league::league_routes, race::race_routes, session::session_routes,
+        standings::standings_routes,
+    },
+    services::{
+        archive::spawn_session_archiver,
+        cache::{spawn_cache_sweeper, Cache},
+        calendar_sync::spawn_calendar_sync,
+        session_keys::spawn_session_key_reconciler,
     },
-    routes::{race::race_routes, session::session_routes, standings::standings_routes},
     utils::{config::Config, state::AppState},
 };
 
-pub async fn make_app() -> Result<Router, Box<dyn Error>> {
+/// Logs to stdout, at `LOG_LEVEL` for this crate.
+pub fn init_tracing() {
     let log_level = std::env::var("LOG_LEVEL")
         .unwrap_or_else(|_| "info".to_string())
         .to_lowercase();
@@ -52,11 +58,11 @@ pub async fn make_app() -> Result<Router, Box<dyn Error>> {
     let tracing_layer = tracing_subscriber::fmt::layer();
 
     Registry::default().with(tracing_layer).with(filter).init();
+}
 
-    info!("Initializing application...");
-    let config = Config::init();
-
-    info!("Configuration loaded successfully");
+/// Connects to the database and builds the state shared by the server and
+/// `f1ctl`. No background jobs are started.
+pub async fn build_state(config: Config) -> Result<Arc<AppState>, Box<dyn Error>> {
     let connect_options = PgConnectOptions::from_str(&config.db_url)?.statement_cache_capacity(0);
     // Create database connection pool
     let db_pool = PgPoolOptions::new()
@@ -69,37 +75,50 @@ pub async fn make_app() -> Result<Router, Box<dyn Error>> {
 
     info!("Database connection pool created successfully");
     let http_client = reqwest::Client::new();
+    let openf1 = OpenF1Client::new(http_client.clone(), &config.openf1_base_url);
+    let jolpica = JolpicaClient::new(http_client.clone(), &config.jolpica_base_url);
     info!("External clients initialized successfully");
 
-    let fetch_driver_telemetry_cache: DashMap<String, CacheEntry<Vec<SpeedDistance>>> =
-        DashMap::new();
-    let get_drivers_position_telemetry_cache: DashMap<String, CacheEntry<Vec<DriverLapGraph>>> =
-        DashMap::new();
-    let get_sector_timings_cache: DashMap<String, CacheEntry<Vec<FastestLapSector>>> =
-        DashMap::new();
-    let get_race_pace_cache: DashMap<String, CacheEntry<Vec<PacePoint>>> = DashMap::new();
-    let quali_session_cache: DashMap<String, CacheEntry<QualifyingRankings>> = DashMap::new();
+    let cache = Cache::new(db_pool.clone());
 
     let state = Arc::new(AppState {
         db_pool,
         config,
         http_client,
-        fetch_driver_telemetry_cache,
-        get_drivers_position_telemetry_cache,
-        get_sector_timings_cache,
-        get_race_pace_cache,
-        quali_session_cache
+        openf1,
+        jolpica,
+        cache,
     });
 
+    Ok(state)
+}
+
+pub async fn make_app() -> Result<Router, Box<dyn Error>> {
+    init_tracing();
+
+    info!("Initializing application...");
+    let config = Config::init();
+
+    info!("Configuration loaded successfully");
+    let state = build_state(config).await?;
+
+    spawn_calendar_sync(state.clone());
+    spawn_session_key_reconciler(state.clone());
+    spawn_cache_sweeper(state.clone());
+    spawn_session_archiver(state.clone());
+    info!("Background jobs started");
+
     let value1 = state.clone();
     let value2 = state.clone();
     let app = Router::new()
         .route("/", get(health_check))
         .nest("/auth", auth_routes())
         .nest("/users", user_routes(state.clone()))
+        .nest("/leagues", league_routes(state.clone()))
         .nest("/race", race_routes(state.clone()))
         .nest("/session", session_routes(state.clone()))
         .nest("/standings", standings_routes(state.clone()))
+        .nest("/fantasy", fantasy_routes(state.clone()))
         .route(
             "/get_weather",
             get(get_weather).route_layer(from_fn(move |req, next| {
@@ -120,5 +139,5 @@ pub async fn make_app() -> Result<Router, Box<dyn Error>> {
 }
 
 async fn health_check() -> impl IntoResponse {
-    return (StatusCode::OK, Json(json!({"message": "Hello World"}))).into_response();
+    (StatusCode::OK, Json(json!({"message": "Hello World"}))).into_response()
 }
@@ -1,7 +1,10 @@
 use crate::{
     handlers::{
         middleware::auth_middleware,
-        race::{get_all_races_data_db, get_race_data, get_race_results, get_upcoming_race_data},
+        race::{
+            get_all_races_data_db, get_lock_status, get_race_data, get_race_results,
+            get_season_race_results, get_upcoming_race_data,
+        },
     },
     utils::state::AppState,
 };
@@ -12,9 +15,14 @@ pub fn race_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
     let race_router = Router::new()
         .route("/get_race_results", get(get_race_results))
         .route("/get_race_results/{round}", get(get_race_results))
+        .route(
+            "/get_race_results/{year}/{round}",
+            get(get_season_race_results),
+        )
         .route("/get_all_races_data/{year}", get(get_all_races_data_db))
         .route("/get_upcoming_race_data", get(get_upcoming_race_data))
         .route("/get_race_data/{year}/{round}", get(get_race_data))
+        .route("/{year}/{round}/lock_status", get(get_lock_status))
         .with_state(state.clone());
     race_router.layer(from_fn(move |req, next| {
         auth_middleware(State(state.clone()), req, next)
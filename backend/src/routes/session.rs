@@ -1,10 +1,11 @@
 use crate::{
     handlers::{
-        middleware::auth_middleware,
+        middleware::{admin_middleware, auth_middleware},
         session::{
-            compare_race_pace, fetch_driver_telemetry, get_drivers_position_telemetry,
+            compare_minisectors, compare_race_pace, fetch_driver_telemetry, get_driver_telemetry,
+            get_drivers_position_telemetry, get_lap_delta, get_long_runs, get_pit_stops,
             get_quali_session_data, get_sector_timings, get_session_data, get_sessions,
-            get_sprint_quali_session_data,
+            get_sprint_quali_session_data, get_stints, get_unresolved_sessions,
         },
     },
     utils::state::AppState,
@@ -24,14 +25,33 @@ pub fn session_routes(state: Arc<AppState>) -> Router<Arc<AppState>> {
             "/fetch_driver_telemetry/{session_key}/{driver_number}",
             get(fetch_driver_telemetry),
         )
+        .route(
+            "/driver_telemetry/{session_key}/{driver_number}",
+            get(get_driver_telemetry),
+        )
         .route(
             "/get_drivers_position_telemetry/{session_key}",
             get(get_drivers_position_telemetry),
         )
         .route("/get_sector_timings/{session_key}", get(get_sector_timings))
+        .route("/get_stints/{session_key}", get(get_stints))
+        .route("/get_pit_stops/{session_key}", get(get_pit_stops))
+        .route("/get_long_runs/{year}/{round}", get(get_long_runs))
         .route("/get_sprint_quali_session_data/{session_key}", get(get_sprint_quali_session_data))
         .route("/compare_race_pace/{session_key}", get(compare_race_pace))
+        .route("/lap_delta/{session_key}", get(get_lap_delta))
+        .route("/compare_minisectors/{session_key}", get(compare_minisectors))
+        .with_state(state.clone());
+
+    // Operator view of the session key reconciler
+    let admin_state = state.clone();
+    let admin_router = Router::new()
+        .route("/unresolved_sessions", get(get_unresolved_sessions))
+        .route_layer(from_fn(move |req, next| {
+            admin_middleware(State(admin_state.clone()), req, next)
+        }))
         .with_state(state.clone());
+    let session_router = session_router.merge(admin_router);
 
     session_router.layer(from_fn(move |req, next| {
         auth_middleware(State(state.clone()), req, next)
@@ -0,0 +1,514 @@
+use std::{
+    collections::BTreeSet,
+    io::{Read, Write},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{Duration as ChronoDuration, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{de::DeserializeOwned, Serialize};
+use sqlx::{PgPool, Postgres, Transaction};
+use tokio::time::{sleep, MissedTickBehavior};
+use tracing::{error, info, warn};
+
+use crate::{
+    clients::openf1::Window,
+    models::{
+        error::Error,
+        openf1::{
+            CarData, Interval, Lap, Location, PitStop, Position, RaceControlMessage, SessionDriver,
+            Stint, Weather,
+        },
+        session::ArchiveReport,
+    },
+    services::schedule::SESSION_COMPLETE_HOURS,
+    utils::state::AppState,
+};
+
+/// Pause between OpenF1 requests while archiving, to stay under its rate
+/// limit.
+const REQUEST_DELAY_MS: u64 = 300;
+/// driver_number of datasets that cover every driver.
+const ALL_DRIVERS: i32 = 0;
+/// Sessions archived per run of the archiver, each takes a few minutes of
+/// OpenF1 requests.
+const SESSIONS_PER_RUN: i64 = 3;
+/// Failed runs after which a session is left to operators.
+pub const MAX_ATTEMPTS: i32 = 6;
+/// Wait before retrying a failed session, doubled after every attempt.
+const RETRY_DELAY_MINUTES: i32 = 30;
+
+/// OpenF1 endpoints kept in "SessionArchive".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dataset {
+    Laps,
+    CarData,
+    Location,
+    Position,
+    Drivers,
+    Stints,
+    Pit,
+    Intervals,
+    Weather,
+    RaceControl,
+}
+
+impl Dataset {
+    fn name(self) -> &'static str {
+        match self {
+            Dataset::Laps => "laps",
+            Dataset::CarData => "car_data",
+            Dataset::Location => "location",
+            Dataset::Position => "position",
+            Dataset::Drivers => "drivers",
+            Dataset::Stints => "stints",
+            Dataset::Pit => "pit",
+            Dataset::Intervals => "intervals",
+            Dataset::Weather => "weather",
+            Dataset::RaceControl => "race_control",
+        }
+    }
+}
+
+fn compress<T: Serialize>(rows: &[T]) -> Result<Vec<u8>, Error> {
+    let json = serde_json::to_vec(rows)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json)?;
+    Ok(encoder.finish()?)
+}
+
+fn decompress<T: DeserializeOwned>(payload: &[u8]) -> Result<Vec<T>, Error> {
+    let mut json = Vec::new();
+    GzDecoder::new(payload).read_to_end(&mut json)?;
+    // Archived rows were written by us, so a mismatch is not an upstream error
+    serde_json::from_slice(&json).map_err(|e| {
+        error!("Corrupt archive payload: {:?}", e);
+        Error::new(
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            "Corrupt session archive",
+        )
+    })
+}
+
+/// Whether every dataset of the session is stored. Laps are written last, so
+/// a session whose archiving was interrupted only has telemetry rows.
+pub async fn is_archived(db: &PgPool, session_key: u32) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM "SessionArchive"
+            WHERE session_key = $1 AND dataset = $2 AND driver_number = $3
+        )
+        "#,
+    )
+    .bind(session_key as i32)
+    .bind(Dataset::Laps.name())
+    .bind(ALL_DRIVERS)
+    .fetch_one(db)
+    .await
+}
+
+/// Drivers whose telemetry an earlier, interrupted run already stored.
+async fn archived_drivers(db: &PgPool, session_key: u32) -> Result<BTreeSet<u32>, sqlx::Error> {
+    let drivers: Vec<i32> = sqlx::query_scalar(
+        r#"
+        SELECT driver_number FROM "SessionArchive"
+        WHERE session_key = $1 AND dataset = $2
+        "#,
+    )
+    .bind(session_key as i32)
+    .bind(Dataset::Location.name())
+    .fetch_all(db)
+    .await?;
+
+    Ok(drivers.into_iter().map(|d| d as u32).collect())
+}
+
+/// Rows of an archived dataset, `None` when the session is not archived.
+async fn load<T: DeserializeOwned>(
+    db: &PgPool,
+    session_key: u32,
+    dataset: Dataset,
+    driver_number: i32,
+) -> Result<Option<Vec<T>>, Error> {
+    let payload: Option<Vec<u8>> = sqlx::query_scalar(
+        r#"
+        SELECT payload FROM "SessionArchive"
+        WHERE session_key = $1 AND dataset = $2 AND driver_number = $3
+        "#,
+    )
+    .bind(session_key as i32)
+    .bind(dataset.name())
+    .bind(driver_number)
+    .fetch_optional(db)
+    .await?;
+
+    match payload {
+        Some(payload) => Ok(Some(decompress(&payload)?)),
+        // A driver without telemetry has no row in an archived session
+        None if driver_number != ALL_DRIVERS && is_archived(db, session_key).await? => {
+            Ok(Some(Vec::new()))
+        }
+        None => Ok(None),
+    }
+}
+
+async fn save<T: Serialize>(
+    tx: &mut Transaction<'_, Postgres>,
+    session_key: u32,
+    dataset: Dataset,
+    driver_number: i32,
+    rows: &[T],
+) -> Result<usize, Error> {
+    sqlx::query(
+        r#"
+        INSERT INTO "SessionArchive" (session_key, dataset, driver_number, payload, row_count)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (session_key, dataset, driver_number) DO NOTHING
+        "#,
+    )
+    .bind(session_key as i32)
+    .bind(dataset.name())
+    .bind(driver_number)
+    .bind(compress(rows)?)
+    .bind(rows.len() as i32)
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(rows.len())
+}
+
+fn in_window(date: chrono::DateTime<Utc>, window: Option<Window>) -> bool {
+    window.is_none_or(|(from, to)| date > from && date < to)
+}
+
+// Readers used by the session handlers. They serve archived sessions from
+// "SessionArchive" and fall back to OpenF1 for the others.
+
+pub async fn laps(
+    state: &AppState,
+    session_key: u32,
+    driver_number: Option<u32>,
+) -> Result<Vec<Lap>, Error> {
+    match load::<Lap>(&state.db_pool, session_key, Dataset::Laps, ALL_DRIVERS).await? {
+        Some(laps) => Ok(laps
+            .into_iter()
+            .filter(|l| driver_number.is_none_or(|d| l.driver_number == d))
+            .collect()),
+        None => Ok(state.openf1.laps(session_key, driver_number).await?),
+    }
+}
+
+pub async fn car_data(
+    state: &AppState,
+    session_key: u32,
+    driver_number: u32,
+    window: Option<Window>,
+) -> Result<Vec<CarData>, Error> {
+    let driver = driver_number as i32;
+    match load::<CarData>(&state.db_pool, session_key, Dataset::CarData, driver).await? {
+        Some(rows) => Ok(rows
+            .into_iter()
+            .filter(|r| in_window(r.date, window))
+            .collect()),
+        None => Ok(state
+            .openf1
+            .car_data(session_key, driver_number, window)
+            .await?),
+    }
+}
+
+pub async fn location(
+    state: &AppState,
+    session_key: u32,
+    driver_number: u32,
+    window: Option<Window>,
+) -> Result<Vec<Location>, Error> {
+    let driver = driver_number as i32;
+    match load::<Location>(&state.db_pool, session_key, Dataset::Location, driver).await? {
+        Some(rows) => Ok(rows
+            .into_iter()
+            .filter(|r| in_window(r.date, window))
+            .collect()),
+        None => Ok(state
+            .openf1
+            .location(session_key, driver_number, window)
+            .await?),
+    }
+}
+
+pub async fn position(state: &AppState, session_key: u32) -> Result<Vec<Position>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Position, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.position(session_key).await?),
+    }
+}
+
+pub async fn weather(state: &AppState, session_key: u32) -> Result<Vec<Weather>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Weather, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.session_weather(session_key).await?),
+    }
+}
+
+pub async fn drivers(state: &AppState, session_key: u32) -> Result<Vec<SessionDriver>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Drivers, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.drivers(session_key).await?),
+    }
+}
+
+pub async fn stints(state: &AppState, session_key: u32) -> Result<Vec<Stint>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Stints, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.stints(session_key).await?),
+    }
+}
+
+pub async fn pit(state: &AppState, session_key: u32) -> Result<Vec<PitStop>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Pit, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.pit(session_key).await?),
+    }
+}
+
+pub async fn intervals(state: &AppState, session_key: u32) -> Result<Vec<Interval>, Error> {
+    match load(&state.db_pool, session_key, Dataset::Intervals, ALL_DRIVERS).await? {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.intervals(session_key).await?),
+    }
+}
+
+pub async fn race_control(
+    state: &AppState,
+    session_key: u32,
+) -> Result<Vec<RaceControlMessage>, Error> {
+    match load(
+        &state.db_pool,
+        session_key,
+        Dataset::RaceControl,
+        ALL_DRIVERS,
+    )
+    .await?
+    {
+        Some(rows) => Ok(rows),
+        None => Ok(state.openf1.race_control(session_key).await?),
+    }
+}
+
+async fn pause() {
+    sleep(Duration::from_millis(REQUEST_DELAY_MS)).await;
+}
+
+/// Downloads every dataset of a session from OpenF1 and stores it in
+/// "SessionArchive". Telemetry is written per driver as it arrives, the
+/// session-wide datasets in one transaction at the end. An interrupted run
+/// resumes with the drivers it had not stored yet.
+pub async fn archive_session(state: &AppState, session_key: u32) -> Result<ArchiveReport, Error> {
+    let mut report = ArchiveReport {
+        session_key,
+        ..Default::default()
+    };
+    if is_archived(&state.db_pool, session_key).await? {
+        report.skipped = true;
+        return Ok(report);
+    }
+
+    let openf1 = &state.openf1;
+    let laps = openf1.laps(session_key, None).await?;
+    if laps.is_empty() {
+        return Err(Error::new(
+            http::StatusCode::NOT_FOUND,
+            "OpenF1 has no laps for this session",
+        ));
+    }
+    pause().await;
+    let positions = openf1.position(session_key).await?;
+    pause().await;
+    let session_drivers = openf1.drivers(session_key).await?;
+    pause().await;
+    let stints = openf1.stints(session_key).await?;
+    pause().await;
+    let pits = openf1.pit(session_key).await?;
+    pause().await;
+    let intervals = openf1.intervals(session_key).await?;
+    pause().await;
+    let weather = openf1.session_weather(session_key).await?;
+    pause().await;
+    let race_control = openf1.race_control(session_key).await?;
+
+    let drivers: BTreeSet<u32> = laps
+        .iter()
+        .map(|l| l.driver_number)
+        .chain(positions.iter().map(|p| p.driver_number))
+        .collect();
+
+    let stored = archived_drivers(&state.db_pool, session_key).await?;
+    for &driver in drivers.difference(&stored) {
+        pause().await;
+        let car_data = openf1.car_data(session_key, driver, None).await?;
+        pause().await;
+        let locations = openf1.location(session_key, driver, None).await?;
+
+        let driver = driver as i32;
+        let mut tx = state.db_pool.begin().await?;
+        report.rows += save(&mut tx, session_key, Dataset::CarData, driver, &car_data).await?;
+        report.rows += save(&mut tx, session_key, Dataset::Location, driver, &locations).await?;
+        tx.commit().await?;
+    }
+
+    let mut tx = state.db_pool.begin().await?;
+    report.rows += save(
+        &mut tx,
+        session_key,
+        Dataset::Position,
+        ALL_DRIVERS,
+        &positions,
+    )
+    .await?;
+    report.rows += save(
+        &mut tx,
+        session_key,
+        Dataset::Drivers,
+        ALL_DRIVERS,
+        &session_drivers,
+    )
+    .await?;
+    report.rows += save(&mut tx, session_key, Dataset::Stints, ALL_DRIVERS, &stints).await?;
+    report.rows += save(&mut tx, session_key, Dataset::Pit, ALL_DRIVERS, &pits).await?;
+    report.rows += save(
+        &mut tx,
+        session_key,
+        Dataset::Intervals,
+        ALL_DRIVERS,
+        &intervals,
+    )
+    .await?;
+    report.rows += save(
+        &mut tx,
+        session_key,
+        Dataset::Weather,
+        ALL_DRIVERS,
+        &weather,
+    )
+    .await?;
+    report.rows += save(
+        &mut tx,
+        session_key,
+        Dataset::RaceControl,
+        ALL_DRIVERS,
+        &race_control,
+    )
+    .await?;
+    // Marks the session as complete, see `is_archived`
+    report.rows += save(&mut tx, session_key, Dataset::Laps, ALL_DRIVERS, &laps).await?;
+    sqlx::query(r#"DELETE FROM "ArchiveFailures" WHERE session_key = $1"#)
+        .bind(session_key as i32)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+
+    report.drivers = drivers.len();
+    info!(
+        "Archived session {}: {} drivers, {} rows",
+        session_key, report.drivers, report.rows
+    );
+    Ok(report)
+}
+
+/// Runs `archive_completed_sessions` every `SESSION_ARCHIVE_INTERVAL_SECS`.
+pub fn spawn_session_archiver(state: Arc<AppState>) {
+    let secs = state.config.session_archive_interval_secs;
+    if secs == 0 {
+        info!("Session archiver disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            match archive_completed_sessions(&state).await {
+                Ok(0) => {}
+                Ok(archived) => info!("Session archiver stored {} sessions", archived),
+                Err(e) => error!("Session archiver failed: {:?}", e),
+            }
+        }
+    });
+}
+
+async fn record_failure(state: &AppState, session_key: i32, reason: &str) -> Result<(), Error> {
+    warn!("Archiving session {} failed: {}", session_key, reason);
+
+    sqlx::query(
+        r#"
+        INSERT INTO "ArchiveFailures" (session_key, reason)
+        VALUES ($1, $2)
+        ON CONFLICT (session_key)
+        DO UPDATE SET
+            reason = EXCLUDED.reason,
+            attempts = "ArchiveFailures".attempts + 1,
+            last_attempt_at = now()
+        "#,
+    )
+    .bind(session_key)
+    .bind(reason)
+    .execute(&state.db_pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Archives up to `SESSIONS_PER_RUN` sessions of the calendar that are over
+/// and have a session_key but no archive yet. Failed sessions are retried
+/// with a growing delay and dropped after `MAX_ATTEMPTS`, see
+/// "ArchiveFailures". Returns how many were stored.
+pub async fn archive_completed_sessions(state: &AppState) -> Result<usize, Error> {
+    let cutoff = Utc::now().naive_utc() - ChronoDuration::hours(SESSION_COMPLETE_HOURS);
+    let session_keys: Vec<i32> = sqlx::query_scalar(
+        r#"
+        SELECT s.session_key
+        FROM "Sessions" s
+        LEFT JOIN "ArchiveFailures" f ON f.session_key = s.session_key
+        WHERE s.session_key IS NOT NULL
+        AND s."date" + COALESCE(s."time", TIME '00:00') < $1
+        AND NOT EXISTS (
+            SELECT 1 FROM "SessionArchive" a
+            WHERE a.session_key = s.session_key
+            AND a.dataset = $2
+            AND a.driver_number = $3
+        )
+        AND (
+            f.session_key IS NULL
+            OR (
+                f.attempts < $4
+                AND f.last_attempt_at + make_interval(mins => $5 << (f.attempts - 1)) <= now()
+            )
+        )
+        ORDER BY s."date" ASC
+        LIMIT $6
+        "#,
+    )
+    .bind(cutoff)
+    .bind(Dataset::Laps.name())
+    .bind(ALL_DRIVERS)
+    .bind(MAX_ATTEMPTS)
+    .bind(RETRY_DELAY_MINUTES)
+    .bind(SESSIONS_PER_RUN)
+    .fetch_all(&state.db_pool)
+    .await?;
+
+    let mut archived = 0;
+    for session_key in session_keys {
+        match archive_session(state, session_key as u32).await {
+            Ok(report) if !report.skipped => archived += 1,
+            Ok(_) => {}
+            Err(e) => record_failure(state, session_key, &e.to_string()).await?,
+        }
+    }
+
+    Ok(archived)
+}
@@ -0,0 +1,389 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{Duration as ChronoDuration, Utc};
+use dashmap::DashMap;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::{types::Json, PgPool};
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    models::cache::CacheEntry, services::schedule::session_completed, utils::state::AppState,
+};
+
+/// Group of cached values sharing a TTL and a size bound.
+#[derive(Debug, Clone, Copy)]
+pub struct Namespace {
+    pub name: &'static str,
+    pub ttl_secs: i64,
+    /// Entries kept before the least recently used one is evicted.
+    pub capacity: usize,
+    /// Also kept in "TelemetryCache", surviving restarts and shared between
+    /// replicas.
+    pub persistent: bool,
+}
+
+impl Namespace {
+    pub const fn new(name: &'static str, ttl_secs: i64, capacity: usize) -> Self {
+        Self {
+            name,
+            ttl_secs,
+            capacity,
+            persistent: false,
+        }
+    }
+
+    pub const fn persistent(mut self) -> Self {
+        self.persistent = true;
+        self
+    }
+}
+
+type Value = Arc<dyn Any + Send + Sync>;
+
+#[derive(Default)]
+struct Store {
+    entries: HashMap<String, CacheEntry<Value>>,
+    tick: u64,
+}
+
+impl Store {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn evict_lru(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| key.clone());
+        if let Some(key) = oldest {
+            self.entries.remove(&key);
+        }
+    }
+}
+
+/// Handle on the lock of a key being computed. Removes the lock from the map
+/// when the last caller lets go of it, also when its future is dropped
+/// mid-computation.
+struct InFlight<'a> {
+    map: &'a DashMap<String, Arc<AsyncMutex<()>>>,
+    key: String,
+    lock: Arc<AsyncMutex<()>>,
+}
+
+impl<'a> InFlight<'a> {
+    fn join(map: &'a DashMap<String, Arc<AsyncMutex<()>>>, key: String) -> Self {
+        let lock = map.entry(key.clone()).or_default().clone();
+        Self { map, key, lock }
+    }
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        // Only the map and this handle still hold the lock when nobody waits
+        self.map
+            .remove_if(&self.key, |_, lock| Arc::strong_count(lock) <= 2);
+    }
+}
+
+/// Cache shared by the handlers, holding any `Clone` value per namespace and
+/// key in memory, backed by Postgres for persistent namespaces.
+#[derive(Clone)]
+pub struct Cache {
+    db: PgPool,
+    namespaces: Arc<DashMap<&'static str, Mutex<Store>>>,
+    // One lock per key being computed, so concurrent misses wait for the
+    // first computation instead of repeating it
+    in_flight: Arc<DashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+impl Cache {
+    pub fn new(db: PgPool) -> Self {
+        Self {
+            db,
+            namespaces: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    fn get<T: Clone + Send + Sync + 'static>(&self, ns: &Namespace, key: &str) -> Option<T> {
+        let store = self.namespaces.get(ns.name)?;
+        let mut store = store.lock().unwrap();
+        let tick = store.next_tick();
+
+        let entry = store.entries.get_mut(key)?;
+        if entry.is_expired() {
+            store.entries.remove(key);
+            return None;
+        }
+        entry.last_used = tick;
+        entry
+            .value
+            .clone()
+            .downcast::<T>()
+            .ok()
+            .map(|v| (*v).clone())
+    }
+
+    fn insert<T: Send + Sync + 'static>(&self, ns: &Namespace, key: String, value: T) {
+        let store = self.namespaces.entry(ns.name).or_default();
+        let mut store = store.lock().unwrap();
+        let tick = store.next_tick();
+
+        store
+            .entries
+            .insert(key, CacheEntry::new(Arc::new(value), ns.ttl_secs, tick));
+        while store.entries.len() > ns.capacity {
+            store.evict_lru();
+        }
+    }
+
+    async fn load<T: DeserializeOwned>(&self, ns: &Namespace, key: &str) -> Option<T> {
+        let value: Result<Option<JsonValue>, _> = sqlx::query_scalar(
+            r#"
+            SELECT value FROM "TelemetryCache"
+            WHERE namespace = $1 AND key = $2
+            AND (expires_at IS NULL OR expires_at > now())
+            "#,
+        )
+        .bind(ns.name)
+        .bind(key)
+        .fetch_optional(&self.db)
+        .await;
+
+        match value {
+            Ok(value) => value.and_then(|v| serde_json::from_value(v).ok()),
+            Err(e) => {
+                warn!(
+                    "Reading {}:{} from TelemetryCache failed: {}",
+                    ns.name, key, e
+                );
+                None
+            }
+        }
+    }
+
+    async fn store<T: Serialize>(&self, ns: &Namespace, key: &str, value: &T, permanent: bool) {
+        let expires_at = (!permanent).then(|| Utc::now() + ChronoDuration::seconds(ns.ttl_secs));
+        let res = sqlx::query(
+            r#"
+            INSERT INTO "TelemetryCache" (namespace, key, value, expires_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (namespace, key)
+            DO UPDATE SET
+                value = EXCLUDED.value,
+                expires_at = EXCLUDED.expires_at,
+                created_at = now()
+            "#,
+        )
+        .bind(ns.name)
+        .bind(key)
+        .bind(Json(value))
+        .bind(expires_at)
+        .execute(&self.db)
+        .await;
+
+        if let Err(e) = res {
+            warn!(
+                "Writing {}:{} to TelemetryCache failed: {}",
+                ns.name, key, e
+            );
+        }
+    }
+
+    /// Returns the cached value for `key`, or runs `compute` and caches its
+    /// result. Concurrent misses for the same key wait for a single
+    /// computation. Errors are not cached, the next waiter then retries.
+    pub async fn get_or_compute<T, E, F, Fut>(
+        &self,
+        ns: &Namespace,
+        key: &str,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.get_or_compute_with(ns, key, None, compute).await
+    }
+
+    /// Like `get_or_compute` for values derived from one session. Once the
+    /// session is over they never change, so persistent namespaces keep them
+    /// without expiry.
+    pub async fn get_or_compute_session<T, E, F, Fut>(
+        &self,
+        ns: &Namespace,
+        session_key: u32,
+        key: &str,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.get_or_compute_with(ns, key, Some(session_key), compute)
+            .await
+    }
+
+    /// Whether a value derived from `session_key` can be kept without expiry.
+    /// Only asked on a miss, as it takes a trip to the database.
+    async fn is_permanent(&self, session_key: Option<u32>) -> bool {
+        let Some(session_key) = session_key else {
+            return false;
+        };
+        session_completed(&self.db, session_key)
+            .await
+            .unwrap_or_else(|e| {
+                warn!("Checking session {} failed: {}", session_key, e);
+                false
+            })
+    }
+
+    async fn get_or_compute_with<T, E, F, Fut>(
+        &self,
+        ns: &Namespace,
+        key: &str,
+        session_key: Option<u32>,
+        compute: F,
+    ) -> Result<T, E>
+    where
+        T: Clone + Serialize + DeserializeOwned + Send + Sync + 'static,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        if let Some(value) = self.get::<T>(ns, key) {
+            info!("CACHE HIT {}:{}", ns.name, key);
+            return Ok(value);
+        }
+
+        let flight = InFlight::join(&self.in_flight, format!("{}:{}", ns.name, key));
+        let result = {
+            let _guard = flight.lock.lock().await;
+            if let Some(value) = self.get::<T>(ns, key) {
+                info!("CACHE HIT {}:{} after waiting", ns.name, key);
+                Ok(value)
+            } else {
+                let stored = match ns.persistent {
+                    true => self.load::<T>(ns, key).await,
+                    false => None,
+                };
+                match stored {
+                    Some(value) => {
+                        info!("CACHE HIT {}:{} in TelemetryCache", ns.name, key);
+                        self.insert(ns, key.to_string(), value.clone());
+                        Ok(value)
+                    }
+                    None => {
+                        info!("CACHE MISS {}:{}, computing…", ns.name, key);
+                        let result = compute().await;
+                        if let Ok(value) = &result {
+                            self.insert(ns, key.to_string(), value.clone());
+                            if ns.persistent {
+                                let permanent = self.is_permanent(session_key).await;
+                                self.store(ns, key, value, permanent).await;
+                            }
+                        }
+                        result
+                    }
+                }
+            }
+        };
+        result
+    }
+
+    /// Drops expired entries of every namespace, returns how many.
+    pub fn sweep(&self) -> usize {
+        let mut removed = 0;
+        for store in self.namespaces.iter() {
+            let mut store = store.lock().unwrap();
+            let before = store.entries.len();
+            store.entries.retain(|_, entry| !entry.is_expired());
+            removed += before - store.entries.len();
+        }
+        removed
+    }
+
+    /// Deletes expired rows of "TelemetryCache", returns how many.
+    pub async fn sweep_persistent(&self) -> Result<u64, sqlx::Error> {
+        let res = sqlx::query(
+            r#"DELETE FROM "TelemetryCache" WHERE expires_at IS NOT NULL AND expires_at <= now()"#,
+        )
+        .execute(&self.db)
+        .await?;
+
+        Ok(res.rows_affected())
+    }
+}
+
+/// Runs `Cache::sweep` and `Cache::sweep_persistent` every
+/// `CACHE_SWEEP_INTERVAL_SECS`, so entries that are never read again do not
+/// stay around.
+pub fn spawn_cache_sweeper(state: Arc<AppState>) {
+    let secs = state.config.cache_sweep_interval_secs;
+    if secs == 0 {
+        info!("Cache sweeper disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let removed = state.cache.sweep();
+            if removed > 0 {
+                debug!("Cache sweeper removed {} expired entries", removed);
+            }
+            match state.cache.sweep_persistent().await {
+                Ok(0) => {}
+                Ok(removed) => debug!("Cache sweeper removed {} expired rows", removed),
+                Err(e) => error!("Sweeping TelemetryCache failed: {:?}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NS: Namespace = Namespace::new("test", 60, 10);
+
+    fn cache() -> Cache {
+        // In-memory namespaces never touch the database
+        Cache::new(PgPool::connect_lazy("postgres://localhost/unused").unwrap())
+    }
+
+    #[tokio::test]
+    async fn releases_the_key_after_computing() {
+        let cache = cache();
+        let value: Result<u32, ()> = cache.get_or_compute(&NS, "k", || async { Ok(1) }).await;
+
+        assert_eq!(value, Ok(1));
+        assert!(cache.in_flight.is_empty());
+    }
+
+    #[tokio::test]
+    async fn releases_the_key_when_the_caller_goes_away() {
+        let cache = cache();
+        let pending = cache.get_or_compute(&NS, "k", std::future::pending::<Result<u32, ()>>);
+        let timed_out = tokio::time::timeout(Duration::from_millis(10), pending).await;
+
+        assert!(timed_out.is_err());
+        assert!(cache.in_flight.is_empty());
+    }
+}
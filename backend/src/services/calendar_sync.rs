@@ -0,0 +1,295 @@
+use std::{sync::Arc, time::Duration};
+
+use chrono::{Datelike, NaiveDate, NaiveTime, Utc};
+use sqlx::{Postgres, Transaction};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::{
+    models::{
+        error::Error,
+        jolpica::{self, Circuit, SessionTime},
+        race::{CalendarSyncReport, Race},
+    },
+    services::results::archive_final_results,
+    utils::{race_utils::parse_jolpica_time, state::AppState},
+};
+
+/// Runs `sync_calendar` for the current season every
+/// `CALENDAR_SYNC_INTERVAL_SECS`, starting right away, then stores any race
+/// results that became final.
+pub fn spawn_calendar_sync(state: Arc<AppState>) {
+    let secs = state.config.calendar_sync_interval_secs;
+    if secs == 0 {
+        info!("Calendar sync disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            let season = Utc::now().year().to_string();
+            match sync_calendar(&state, &season).await {
+                Ok(report) => info!(
+                    "Calendar sync for {} done: {} circuits, {} races, {} sessions changed",
+                    season, report.circuits_changed, report.races_changed, report.sessions_changed
+                ),
+                Err(e) => error!("Calendar sync for {} failed: {:?}", season, e),
+            }
+            if let Err(e) = archive_final_results(&state, &season).await {
+                error!("Archiving results for {} failed: {:?}", season, e);
+            }
+        }
+    });
+}
+
+fn session_times(race: &jolpica::Race) -> Vec<(&'static str, NaiveDate, Option<NaiveTime>)> {
+    let sessions: [(&str, &Option<SessionTime>); 6] = [
+        ("FirstPractice", &race.first_practice),
+        ("SecondPractice", &race.second_practice),
+        ("ThirdPractice", &race.third_practice),
+        ("SprintQualifying", &race.sprint_qualifying),
+        ("Sprint", &race.sprint),
+        ("Qualifying", &race.qualifying),
+    ];
+
+    let mut times: Vec<_> = sessions
+        .into_iter()
+        .filter_map(|(name, session)| {
+            let session = session.as_ref()?;
+            Some((
+                name,
+                session.date,
+                parse_jolpica_time(session.time.as_deref()),
+            ))
+        })
+        .collect();
+    times.push(("Race", race.date, parse_jolpica_time(race.time.as_deref())));
+    times
+}
+
+async fn sync_circuit(
+    tx: &mut Transaction<'_, Postgres>,
+    circuit: &Circuit,
+    report: &mut CalendarSyncReport,
+) -> Result<(), Error> {
+    type CircuitRow = (
+        String,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+        Option<String>,
+    );
+
+    let location = &circuit.location;
+    let wanted: CircuitRow = (
+        circuit.circuit_name.clone(),
+        location.locality.clone(),
+        location.country.clone(),
+        location.lat.clone(),
+        location.long.clone(),
+    );
+
+    let existing: Option<CircuitRow> = sqlx::query_as(
+        r#"
+        SELECT "circuitName", locality, country, lat, long
+        FROM "Circuits"
+        WHERE "circuitId" = $1
+        "#,
+    )
+    .bind(&circuit.circuit_id)
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    let query = match existing {
+        Some(row) if row == wanted => return Ok(()),
+        Some(_) => {
+            report
+                .changes
+                .push(format!("Circuit {} updated", circuit.circuit_id));
+            r#"
+            UPDATE "Circuits"
+            SET "circuitName" = $2, locality = $3, country = $4, lat = $5, long = $6
+            WHERE "circuitId" = $1
+            "#
+        }
+        None => {
+            report
+                .changes
+                .push(format!("Circuit {} added", circuit.circuit_id));
+            r#"
+            INSERT INTO "Circuits" ("circuitId", "circuitName", locality, country, lat, long)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#
+        }
+    };
+
+    sqlx::query(query)
+        .bind(&circuit.circuit_id)
+        .bind(&wanted.0)
+        .bind(&wanted.1)
+        .bind(&wanted.2)
+        .bind(&wanted.3)
+        .bind(&wanted.4)
+        .execute(&mut **tx)
+        .await?;
+    report.circuits_changed += 1;
+
+    Ok(())
+}
+
+async fn sync_race(
+    tx: &mut Transaction<'_, Postgres>,
+    race: &jolpica::Race,
+    report: &mut CalendarSyncReport,
+) -> Result<i64, Error> {
+    let time = parse_jolpica_time(race.time.as_deref());
+    let existing =
+        sqlx::query_as::<_, Race>(r#"SELECT * FROM "Races" WHERE season = $1 AND round = $2"#)
+            .bind(&race.season)
+            .bind(&race.round)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+    let Some(existing) = existing else {
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO "Races" (season, round, date, time, "raceName", "circuitId")
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id
+            "#,
+        )
+        .bind(&race.season)
+        .bind(&race.round)
+        .bind(race.date)
+        .bind(time)
+        .bind(&race.race_name)
+        .bind(&race.circuit.circuit_id)
+        .fetch_one(&mut **tx)
+        .await?;
+
+        report
+            .changes
+            .push(format!("Round {} ({}) added", race.round, race.race_name));
+        report.races_changed += 1;
+        return Ok(id);
+    };
+
+    let unchanged = existing.date == Some(race.date)
+        && existing.time == time
+        && existing.race_name == race.race_name
+        && existing.circuit_id == race.circuit.circuit_id;
+    if unchanged {
+        return Ok(existing.id);
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE "Races"
+        SET date = $2, time = $3, "raceName" = $4, "circuitId" = $5
+        WHERE id = $1
+        "#,
+    )
+    .bind(existing.id)
+    .bind(race.date)
+    .bind(time)
+    .bind(&race.race_name)
+    .bind(&race.circuit.circuit_id)
+    .execute(&mut **tx)
+    .await?;
+
+    report.changes.push(format!(
+        "Round {} ({}) updated: {:?} {:?} -> {} {:?}",
+        race.round, race.race_name, existing.date, existing.time, race.date, time
+    ));
+    report.races_changed += 1;
+
+    Ok(existing.id)
+}
+
+async fn sync_sessions(
+    tx: &mut Transaction<'_, Postgres>,
+    race_id: i64,
+    race: &jolpica::Race,
+    report: &mut CalendarSyncReport,
+) -> Result<(), Error> {
+    for (session_type, date, time) in session_times(race) {
+        let existing: Option<(i32, Option<NaiveDate>, Option<NaiveTime>)> = sqlx::query_as(
+            r#"
+            SELECT id, "date", "time"
+            FROM "Sessions"
+            WHERE "raceId" = $1 AND "sessionType" = $2
+            "#,
+        )
+        .bind(race_id)
+        .bind(session_type)
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        match existing {
+            Some((_, old_date, old_time)) if old_date == Some(date) && old_time == time => continue,
+            Some((id, old_date, old_time)) => {
+                // Session keys stay as they are, only the schedule moves
+                sqlx::query(r#"UPDATE "Sessions" SET "date" = $2, "time" = $3 WHERE id = $1"#)
+                    .bind(id)
+                    .bind(date)
+                    .bind(time)
+                    .execute(&mut **tx)
+                    .await?;
+
+                report.changes.push(format!(
+                    "Round {} {} rescheduled: {:?} {:?} -> {} {:?}",
+                    race.round, session_type, old_date, old_time, date, time
+                ));
+            }
+            None => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO "Sessions" ("raceId", "sessionType", "date", "time")
+                    VALUES ($1, $2, $3, $4)
+                    "#,
+                )
+                .bind(race_id)
+                .bind(session_type)
+                .bind(date)
+                .bind(time)
+                .execute(&mut **tx)
+                .await?;
+
+                report
+                    .changes
+                    .push(format!("Round {} {} added", race.round, session_type));
+            }
+        }
+        report.sessions_changed += 1;
+    }
+
+    Ok(())
+}
+
+/// Upserts the circuits, races and session times of a season from Jolpica.
+/// Running it twice in a row changes nothing the second time.
+pub async fn sync_calendar(state: &AppState, season: &str) -> Result<CalendarSyncReport, Error> {
+    let races = state.jolpica.schedule(season).await?;
+    let mut report = CalendarSyncReport {
+        season: season.to_string(),
+        ..Default::default()
+    };
+
+    let mut tx = state.db_pool.begin().await?;
+    for race in &races {
+        sync_circuit(&mut tx, &race.circuit, &mut report).await?;
+        let race_id = sync_race(&mut tx, race, &mut report).await?;
+        sync_sessions(&mut tx, race_id, race, &mut report).await?;
+    }
+    tx.commit().await?;
+
+    for change in &report.changes {
+        info!("Calendar sync {}: {}", season, change);
+    }
+
+    Ok(report)
+}
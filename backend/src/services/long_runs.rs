@@ -0,0 +1,453 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, Duration, Utc};
+use http::StatusCode;
+
+use crate::{
+    models::{
+        error::Error,
+        openf1::{Lap, PitStop, RaceControlMessage, SessionDriver, Stint},
+        strategy::{LongRunPace, LongRunTable},
+    },
+    services::{archive, schedule::round_session_keys, strategy::UNKNOWN_COMPOUND},
+    utils::state::AppState,
+};
+
+const PRACTICE_SESSIONS: [&str; 2] = ["FirstPractice", "SecondPractice"];
+/// Fewest consecutive laps that count as a long run.
+const MIN_LONG_RUN_LAPS: usize = 5;
+/// Laps slower than this share of the stint's fastest lap are cool-down laps
+/// and end a run.
+const PUSH_LAP_RATIO: f64 = 1.07;
+/// Lap time gained per lap from burning fuel, added back when fuel
+/// correcting.
+const FUEL_EFFECT_SECS_PER_LAP: f64 = 0.06;
+
+/// One long run as (tyre age, lap time) pairs.
+struct Run {
+    driver_number: u32,
+    compound: String,
+    laps: Vec<(f64, f64)>,
+}
+
+fn finish_run(runs: &mut Vec<Run>, stint: &Stint, laps: Vec<(f64, f64)>) {
+    if laps.len() >= MIN_LONG_RUN_LAPS {
+        runs.push(Run {
+            driver_number: stint.driver_number,
+            compound: stint
+                .compound
+                .clone()
+                .unwrap_or_else(|| UNKNOWN_COMPOUND.to_string()),
+            laps,
+        });
+    }
+}
+
+/// Time from a start to an end of neutralisation, open ended when the session
+/// finished before the track went green again.
+type Period = (DateTime<Utc>, Option<DateTime<Utc>>);
+
+/// Periods under yellow or red flags and under the (virtual) safety car.
+/// Yellows are shown per sector, so a flag period lasts until every sector
+/// that went yellow is clear again, or the whole track goes green. Flags and
+/// the safety car are tracked apart, so a sector going green does not end a
+/// safety car period.
+fn neutralised_periods(messages: &[RaceControlMessage]) -> Vec<Period> {
+    let mut messages: Vec<&RaceControlMessage> = messages.iter().collect();
+    messages.sort_by_key(|msg| msg.date);
+
+    let mut periods = Vec::new();
+    let (mut flag, mut safety_car) = (None, None);
+    // Sectors under a flag, `None` for a flag on the whole track
+    let mut flagged: BTreeSet<Option<u32>> = BTreeSet::new();
+    for msg in messages {
+        let text = msg.message.to_uppercase();
+        let sector = match msg.scope.as_deref() {
+            Some("Sector") => msg.sector,
+            _ => None,
+        };
+        match msg.flag.as_deref() {
+            Some("YELLOW") | Some("DOUBLE YELLOW") => {
+                flag.get_or_insert(msg.date);
+                flagged.insert(sector);
+            }
+            Some("RED") => {
+                flag.get_or_insert(msg.date);
+                flagged.insert(None);
+            }
+            Some("GREEN") | Some("CLEAR") => {
+                if sector.is_some() {
+                    flagged.remove(&sector);
+                } else {
+                    flagged.clear();
+                }
+                if flagged.is_empty() {
+                    if let Some(start) = flag.take() {
+                        periods.push((start, Some(msg.date)));
+                    }
+                }
+            }
+            _ => {}
+        }
+        if msg.category.as_deref() == Some("SafetyCar") {
+            if text.contains("DEPLOYED") {
+                safety_car.get_or_insert(msg.date);
+            } else if text.contains("IN THIS LAP") || text.contains("ENDING") {
+                if let Some(start) = safety_car.take() {
+                    periods.push((start, Some(msg.date)));
+                }
+            }
+        }
+    }
+    periods.extend(
+        flag.into_iter()
+            .chain(safety_car)
+            .map(|start| (start, None)),
+    );
+    periods
+}
+
+/// Whether the lap was driven entirely outside neutralised periods.
+fn is_green(lap: &Lap, periods: &[Period]) -> bool {
+    let (Some(start), Some(duration)) = (lap.date_start, lap.lap_duration) else {
+        return false;
+    };
+    let end = start + Duration::milliseconds((duration * 1000.0) as i64);
+
+    !periods
+        .iter()
+        .any(|&(from, to)| from <= end && to.is_none_or(|to| to >= start))
+}
+
+/// Consecutive green-flag push laps of every stint. The last lap of a stint
+/// is dropped as an in-lap when the driver pitted on it or has a later stint.
+fn long_runs(
+    laps: &[Lap],
+    stints: &[Stint],
+    pits: &[PitStop],
+    messages: &[RaceControlMessage],
+) -> Vec<Run> {
+    let periods = neutralised_periods(messages);
+    let mut runs = Vec::new();
+
+    for stint in stints {
+        let (Some(lap_start), Some(lap_end)) = (stint.lap_start, stint.lap_end) else {
+            continue;
+        };
+        let in_lap = pits
+            .iter()
+            .any(|p| p.driver_number == stint.driver_number && p.lap_number == lap_end)
+            || stints.iter().any(|s| {
+                s.driver_number == stint.driver_number && s.stint_number > stint.stint_number
+            });
+        let last_lap = if in_lap {
+            lap_end.saturating_sub(1)
+        } else {
+            lap_end
+        };
+        let mut stint_laps: Vec<&Lap> = laps
+            .iter()
+            .filter(|l| {
+                l.driver_number == stint.driver_number
+                    && l.lap_number >= lap_start
+                    && l.lap_number <= last_lap
+            })
+            .collect();
+        stint_laps.sort_by_key(|l| l.lap_number);
+
+        let Some(fastest) = stint_laps
+            .iter()
+            .filter_map(|l| l.lap_duration)
+            .min_by(|a, b| a.total_cmp(b))
+        else {
+            continue;
+        };
+        let age_at_start = stint.tyre_age_at_start.unwrap_or(0);
+
+        let mut current: Vec<(f64, f64)> = Vec::new();
+        let mut previous_lap = None;
+        for lap in stint_laps {
+            let push = !lap.is_pit_out_lap.unwrap_or(false)
+                && lap
+                    .lap_duration
+                    .is_some_and(|d| d <= fastest * PUSH_LAP_RATIO)
+                && is_green(lap, &periods);
+            let consecutive = previous_lap.is_some_and(|prev| lap.lap_number == prev + 1);
+
+            if !push || !consecutive {
+                finish_run(&mut runs, stint, std::mem::take(&mut current));
+            }
+            if push {
+                let age = (age_at_start + lap.lap_number - lap_start) as f64;
+                current.push((age, lap.lap_duration.unwrap_or_default()));
+                previous_lap = Some(lap.lap_number);
+            } else {
+                previous_lap = None;
+            }
+        }
+        finish_run(&mut runs, stint, current);
+    }
+    runs
+}
+
+/// Least squares slope of lap time over tyre age.
+fn slope(laps: &[(f64, f64)]) -> f64 {
+    let n = laps.len() as f64;
+    let mean_x = laps.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = laps.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var) = (0.0, 0.0);
+    for (x, y) in laps {
+        cov += (x - mean_x) * (y - mean_y);
+        var += (x - mean_x).powi(2);
+    }
+    if var > 0.0 {
+        cov / var
+    } else {
+        0.0
+    }
+}
+
+/// Combines the runs of every driver and compound, weighting each run by its
+/// number of laps, and ranks the drivers on each compound by average pace.
+fn pace_table(runs: &[Run], drivers: &[SessionDriver], fuel_corrected: bool) -> Vec<LongRunPace> {
+    // (runs, laps, total lap time, laps weighted slope)
+    let mut totals: BTreeMap<(&str, u32), (u32, u32, f64, f64)> = BTreeMap::new();
+    for run in runs {
+        let laps: Vec<(f64, f64)> = run
+            .laps
+            .iter()
+            .enumerate()
+            .map(|(i, &(age, time))| {
+                let correction = if fuel_corrected {
+                    FUEL_EFFECT_SECS_PER_LAP * i as f64
+                } else {
+                    0.0
+                };
+                (age, time + correction)
+            })
+            .collect();
+
+        let entry = totals
+            .entry((run.compound.as_str(), run.driver_number))
+            .or_default();
+        entry.0 += 1;
+        entry.1 += laps.len() as u32;
+        entry.2 += laps.iter().map(|(_, time)| time).sum::<f64>();
+        entry.3 += slope(&laps) * laps.len() as f64;
+    }
+
+    let mut table: Vec<LongRunPace> = totals
+        .into_iter()
+        .map(|((compound, driver_number), (runs, laps, time, slope))| {
+            let driver = drivers.iter().find(|d| d.driver_number == driver_number);
+            LongRunPace {
+                rank: 0,
+                driver_number,
+                name_acronym: driver.and_then(|d| d.name_acronym.clone()),
+                team_name: driver.and_then(|d| d.team_name.clone()),
+                compound: compound.to_string(),
+                runs,
+                laps,
+                average_lap_time: time / laps as f64,
+                degradation_per_lap: slope / laps as f64,
+            }
+        })
+        .collect();
+
+    table.sort_by(|a, b| {
+        a.compound
+            .cmp(&b.compound)
+            .then(a.average_lap_time.total_cmp(&b.average_lap_time))
+    });
+    let mut rank = 0;
+    for i in 0..table.len() {
+        rank = if i > 0 && table[i].compound == table[i - 1].compound {
+            rank + 1
+        } else {
+            1
+        };
+        table[i].rank = rank;
+    }
+    table
+}
+
+/// Long-run pace of a round from its FirstPractice and SecondPractice
+/// sessions.
+pub async fn long_run_table(
+    state: &AppState,
+    season: &str,
+    round: i32,
+    fuel_corrected: bool,
+) -> Result<LongRunTable, Error> {
+    let sessions = round_session_keys(&state.db_pool, season, round, &PRACTICE_SESSIONS).await?;
+    if sessions.is_empty() {
+        return Err(Error::new(
+            StatusCode::NOT_FOUND,
+            "No practice sessions with data for this round",
+        ));
+    }
+
+    let mut runs = Vec::new();
+    let mut drivers = Vec::new();
+    for (_, session_key) in &sessions {
+        let session_key = *session_key as u32;
+        let laps = archive::laps(state, session_key, None).await?;
+        let stints = archive::stints(state, session_key).await?;
+        let pits = archive::pit(state, session_key).await?;
+        let messages = archive::race_control(state, session_key).await?;
+        runs.extend(long_runs(&laps, &stints, &pits, &messages));
+
+        for driver in archive::drivers(state, session_key).await? {
+            if !drivers
+                .iter()
+                .any(|d: &SessionDriver| d.driver_number == driver.driver_number)
+            {
+                drivers.push(driver);
+            }
+        }
+    }
+
+    Ok(LongRunTable {
+        season: season.to_string(),
+        round,
+        fuel_corrected,
+        session_keys: sessions.iter().map(|(_, key)| *key).collect(),
+        pace: pace_table(&runs, &drivers, fuel_corrected),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn lap(lap_number: u32) -> Lap {
+        Lap {
+            session_key: 1,
+            driver_number: 1,
+            lap_number,
+            date_start: Some(at(lap_number as i64 * 90)),
+            lap_duration: Some(90.0),
+            duration_sector_1: None,
+            duration_sector_2: None,
+            duration_sector_3: None,
+            i1_speed: None,
+            i2_speed: None,
+            st_speed: None,
+            is_pit_out_lap: Some(false),
+        }
+    }
+
+    fn stint(stint_number: u32, lap_start: u32, lap_end: u32) -> Stint {
+        Stint {
+            driver_number: 1,
+            stint_number,
+            compound: Some("MEDIUM".to_string()),
+            lap_start: Some(lap_start),
+            lap_end: Some(lap_end),
+            tyre_age_at_start: Some(0),
+        }
+    }
+
+    fn message(secs: i64, category: &str, flag: Option<&str>, text: &str) -> RaceControlMessage {
+        RaceControlMessage {
+            date: at(secs),
+            category: Some(category.to_string()),
+            flag: flag.map(str::to_string),
+            scope: flag.map(|_| "Track".to_string()),
+            sector: None,
+            driver_number: None,
+            lap_number: None,
+            message: text.to_string(),
+            qualifying_phase: None,
+        }
+    }
+
+    fn sector_flag(secs: i64, flag: &str, sector: u32) -> RaceControlMessage {
+        RaceControlMessage {
+            scope: Some("Sector".to_string()),
+            sector: Some(sector),
+            ..message(
+                secs,
+                "Flag",
+                Some(flag),
+                &format!("{} IN TRACK SECTOR {}", flag, sector),
+            )
+        }
+    }
+
+    #[test]
+    fn laps_inside_a_flag_period_are_not_green() {
+        // Yellow shown during lap 2, cleared during lap 4
+        let messages = [sector_flag(200, "YELLOW", 5), sector_flag(400, "CLEAR", 5)];
+        let periods = neutralised_periods(&messages);
+
+        assert!(is_green(&lap(1), &periods));
+        assert!(!is_green(&lap(3), &periods));
+        assert!(is_green(&lap(5), &periods));
+    }
+
+    #[test]
+    fn safety_car_lasts_until_it_comes_in() {
+        // The yellow that brought out the safety car is cleared under it
+        let messages = [
+            sector_flag(90, "YELLOW", 2),
+            message(100, "SafetyCar", None, "SAFETY CAR DEPLOYED"),
+            sector_flag(150, "CLEAR", 2),
+            message(500, "SafetyCar", None, "SAFETY CAR IN THIS LAP"),
+        ];
+        let periods = neutralised_periods(&messages);
+
+        assert!(!is_green(&lap(3), &periods));
+        assert!(is_green(&lap(6), &periods));
+    }
+
+    #[test]
+    fn flag_periods_last_until_every_sector_is_clear() {
+        // Sector 5 stays yellow after sector 3 is cleared during lap 2
+        let messages = [
+            sector_flag(150, "YELLOW", 3),
+            sector_flag(200, "YELLOW", 5),
+            sector_flag(250, "CLEAR", 3),
+            sector_flag(400, "CLEAR", 5),
+        ];
+        let periods = neutralised_periods(&messages);
+
+        assert!(!is_green(&lap(3), &periods));
+        assert!(is_green(&lap(5), &periods));
+    }
+
+    #[test]
+    fn track_green_clears_every_sector() {
+        let messages = [
+            sector_flag(150, "YELLOW", 3),
+            message(200, "Flag", Some("RED"), "RED FLAG"),
+            message(400, "Flag", Some("GREEN"), "GREEN LIGHT - PIT EXIT OPEN"),
+        ];
+        let periods = neutralised_periods(&messages);
+
+        assert!(!is_green(&lap(3), &periods));
+        assert!(is_green(&lap(5), &periods));
+    }
+
+    #[test]
+    fn unended_periods_cover_the_rest_of_the_session() {
+        let messages = [message(300, "Flag", Some("RED"), "RED FLAG")];
+
+        assert!(!is_green(&lap(8), &neutralised_periods(&messages)));
+    }
+
+    #[test]
+    fn last_lap_is_kept_without_a_following_stint_or_pit() {
+        let laps: Vec<Lap> = (1..=6).map(lap).collect();
+
+        let runs = long_runs(&laps, &[stint(1, 1, 6)], &[], &[]);
+        assert_eq!(runs[0].laps.len(), 6);
+
+        let runs = long_runs(&laps, &[stint(1, 1, 6), stint(2, 7, 10)], &[], &[]);
+        assert_eq!(runs[0].laps.len(), 5);
+    }
+}
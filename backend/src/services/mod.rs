@@ -0,0 +1,11 @@
+pub mod archive;
+pub mod cache;
+pub mod calendar_sync;
+pub mod long_runs;
+pub mod pricing;
+pub mod results;
+pub mod schedule;
+pub mod scoring;
+pub mod session_keys;
+pub mod strategy;
+pub mod telemetry;
@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+
+use crate::{
+    models::{error::Error, fantasy::FantasyAsset},
+    services::scoring::picks_for_round,
+    utils::state::AppState,
+};
+
+/// Number of scored rounds averaged for price changes.
+pub const ROLLING_ROUNDS: i32 = 3;
+pub const MIN_PRICE: f64 = 3.0;
+pub const MAX_PRICE_CHANGE: f64 = 0.6;
+
+/// Price change of an asset after a round, based on its average points per
+/// round relative to its price, nudged by how many teams own it.
+pub fn price_change(price: f64, rolling_points: f64, ownership: f64) -> f64 {
+    let points_per_million = rolling_points / price.max(MIN_PRICE);
+
+    let performance: f64 = match points_per_million {
+        p if p >= 1.2 => 0.3,
+        p if p >= 0.9 => 0.1,
+        p if p >= 0.6 => 0.0,
+        p if p >= 0.3 => -0.1,
+        _ => -0.3,
+    };
+    let demand = match ownership {
+        o if o >= 0.4 => 0.1,
+        o if o < 0.05 => -0.1,
+        _ => 0.0,
+    };
+
+    let delta = (performance + demand).clamp(-MAX_PRICE_CHANGE, MAX_PRICE_CHANGE);
+    // Keep prices on 0.1 steps and above the floor
+    let new_price = ((price + delta) * 10.0).round() / 10.0;
+    new_price.max(MIN_PRICE) - price
+}
+
+/// Moves every asset price of the season after `round` has been scored and
+/// records it in the price history. Re-running a round replaces its change;
+/// changes of later rounds are kept and their recorded prices shifted along.
+pub async fn update_prices(state: &AppState, season: &str, round: i32) -> Result<usize, Error> {
+    let assets =
+        sqlx::query_as::<_, FantasyAsset>(r#"SELECT * FROM "FantasyAssets" WHERE season = $1"#)
+            .bind(season)
+            .fetch_all(&state.db_pool)
+            .await?;
+
+    let rolling_points: HashMap<i32, f64> = sqlx::query_as::<_, (i32, f64)>(
+        r#"
+        SELECT asset_id, SUM(points) / COUNT(DISTINCT round)
+        FROM "FantasyPoints"
+        WHERE season = $1 AND round <= $2 AND round > $2 - $3
+        GROUP BY asset_id
+        "#,
+    )
+    .bind(season)
+    .bind(round)
+    .bind(ROLLING_ROUNDS)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let teams = picks_for_round(&state.db_pool, season, round).await?;
+    let mut owners: HashMap<i32, usize> = HashMap::new();
+    for team in &teams {
+        for id in team.driver_ids.iter().chain(team.constructor_ids.iter()) {
+            *owners.entry(*id).or_default() += 1;
+        }
+    }
+
+    // (delta already applied for this round, sum of deltas of later rounds)
+    let applied: HashMap<i32, (f64, f64)> = sqlx::query_as::<_, (i32, f64, f64)>(
+        r#"
+        SELECT
+            asset_id,
+            COALESCE(SUM(delta) FILTER (WHERE round = $2), 0),
+            COALESCE(SUM(delta) FILTER (WHERE round > $2), 0)
+        FROM "FantasyAssetPrices"
+        WHERE season = $1
+        GROUP BY asset_id
+        "#,
+    )
+    .bind(season)
+    .bind(round)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .map(|(asset_id, current, later)| (asset_id, (current, later)))
+    .collect();
+
+    let mut tx = state.db_pool.begin().await?;
+    for asset in &assets {
+        // Price before this round: undo this round's previous run and every
+        // later round, so the change is applied once and on the right base
+        let (previous_delta, later_deltas) = applied.get(&asset.id).copied().unwrap_or_default();
+        let base_price = asset.price - previous_delta - later_deltas;
+        let points = rolling_points.get(&asset.id).copied().unwrap_or(0.0);
+        let ownership = if teams.is_empty() {
+            0.0
+        } else {
+            *owners.get(&asset.id).unwrap_or(&0) as f64 / teams.len() as f64
+        };
+
+        let delta = price_change(base_price, points, ownership);
+        let price = base_price + delta;
+
+        sqlx::query(
+            r#"
+            INSERT INTO "FantasyAssetPrices"
+                (asset_id, season, round, price, delta, rolling_points, ownership)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (asset_id, round)
+            DO UPDATE SET
+                price = EXCLUDED.price,
+                delta = EXCLUDED.delta,
+                rolling_points = EXCLUDED.rolling_points,
+                ownership = EXCLUDED.ownership,
+                created_at = now()
+            "#,
+        )
+        .bind(asset.id)
+        .bind(season)
+        .bind(round)
+        .bind(price)
+        .bind(delta)
+        .bind(points)
+        .bind(ownership)
+        .execute(&mut *tx)
+        .await?;
+
+        let shift = delta - previous_delta;
+        sqlx::query(
+            r#"
+            UPDATE "FantasyAssetPrices" SET price = price + $1
+            WHERE asset_id = $2 AND round > $3
+            "#,
+        )
+        .bind(shift)
+        .bind(asset.id)
+        .bind(round)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(r#"UPDATE "FantasyAssets" SET price = $1 WHERE id = $2"#)
+            .bind(price + later_deltas)
+            .bind(asset.id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(assets.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_change(price: f64, rolling_points: f64, ownership: f64, expected: f64) {
+        let delta = price_change(price, rolling_points, ownership);
+        assert!(
+            (delta - expected).abs() < 1e-9,
+            "price {price}, points {rolling_points}, ownership {ownership}: {delta} != {expected}"
+        );
+    }
+
+    #[test]
+    fn performance_moves_the_price() {
+        // Points per million of 1.2, 0.9, 0.6, 0.3 and below at average ownership
+        assert_change(10.0, 12.0, 0.2, 0.3);
+        assert_change(10.0, 9.0, 0.2, 0.1);
+        assert_change(10.0, 6.0, 0.2, 0.0);
+        assert_change(10.0, 3.0, 0.2, -0.1);
+        assert_change(10.0, 0.0, 0.2, -0.3);
+    }
+
+    #[test]
+    fn ownership_nudges_the_price() {
+        assert_change(10.0, 6.0, 0.4, 0.1);
+        assert_change(10.0, 6.0, 0.04, -0.1);
+        assert_change(10.0, 12.0, 0.5, 0.4);
+        assert_change(10.0, 0.0, 0.0, -0.4);
+    }
+
+    #[test]
+    fn prices_stay_on_tenths_and_above_the_floor() {
+        assert_change(10.04, 7.0, 0.2, 10.0 - 10.04);
+        assert_change(3.1, 0.0, 0.0, MIN_PRICE - 3.1);
+        assert_change(MIN_PRICE, 0.0, 0.0, 0.0);
+        // Assets priced below the floor are lifted to it
+        assert_change(1.0, 0.0, 0.2, MIN_PRICE - 1.0);
+    }
+}
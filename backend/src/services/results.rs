@@ -0,0 +1,415 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{types::Json, PgPool};
+use tracing::{info, warn};
+
+use crate::{
+    models::{error::Error, jolpica::Race, openf1::SessionResult},
+    utils::state::AppState,
+};
+
+/// Hours after the start after which a classification is taken as final,
+/// leaving time for stewards' decisions.
+pub const RESULTS_SETTLE_HOURS: i64 = 48;
+/// Syncs after which a settled session without results is left to
+/// operators, see "MissingResults".
+pub const MAX_ATTEMPTS: i32 = 10;
+
+/// Sessions whose final classification is archived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultKind {
+    Race,
+    Qualifying,
+    Sprint,
+}
+
+impl ResultKind {
+    pub const ALL: [ResultKind; 3] = [ResultKind::Race, ResultKind::Qualifying, ResultKind::Sprint];
+
+    /// Kind for a "sessionType" of the "Sessions" table.
+    pub fn from_session_type(session_type: &str) -> Option<Self> {
+        match session_type {
+            "Race" => Some(ResultKind::Race),
+            "Qualifying" => Some(ResultKind::Qualifying),
+            "Sprint" => Some(ResultKind::Sprint),
+            _ => None,
+        }
+    }
+
+    fn session_type(self) -> &'static str {
+        match self {
+            ResultKind::Race => "Race",
+            ResultKind::Qualifying => "Qualifying",
+            ResultKind::Sprint => "Sprint",
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            ResultKind::Race => r#""RaceResults""#,
+            ResultKind::Qualifying => r#""QualifyingResults""#,
+            ResultKind::Sprint => r#""SprintResults""#,
+        }
+    }
+
+    /// Scheduled start of this session of `race`.
+    fn start(self, race: &Race) -> NaiveDateTime {
+        let session = match self {
+            ResultKind::Race => None,
+            ResultKind::Qualifying => race.qualifying.as_ref(),
+            ResultKind::Sprint => race.sprint.as_ref(),
+        };
+        session.map_or_else(|| race.start(), |s| s.start())
+    }
+
+    fn has_results(self, race: &Race) -> bool {
+        match self {
+            ResultKind::Race => !race.results.is_empty(),
+            ResultKind::Qualifying => !race.qualifying_results.is_empty(),
+            ResultKind::Sprint => !race.sprint_results.is_empty(),
+        }
+    }
+
+    async fn fetch(
+        self,
+        state: &AppState,
+        season: &str,
+        round: &str,
+    ) -> Result<Option<Race>, Error> {
+        let race = match self {
+            ResultKind::Race => state.jolpica.results(season, round).await?,
+            ResultKind::Qualifying => state.jolpica.qualifying(season, round).await?,
+            ResultKind::Sprint => state.jolpica.sprint_results(season, round).await?,
+        };
+        Ok(race)
+    }
+}
+
+pub fn is_settled(start: NaiveDateTime) -> bool {
+    start + Duration::hours(RESULTS_SETTLE_HOURS) < Utc::now().naive_utc()
+}
+
+async fn stored_race(
+    db: &PgPool,
+    kind: ResultKind,
+    season: &str,
+    round: i32,
+) -> Result<Option<Race>, sqlx::Error> {
+    let race: Option<Json<Race>> = sqlx::query_scalar(&format!(
+        "SELECT race FROM {} WHERE season = $1 AND round = $2 AND race IS NOT NULL",
+        kind.table()
+    ))
+    .bind(season)
+    .bind(round)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(race.map(|Json(race)| race))
+}
+
+async fn latest_stored_race(
+    db: &PgPool,
+    kind: ResultKind,
+    season: &str,
+) -> Result<Option<Race>, sqlx::Error> {
+    let race: Option<Json<Race>> = sqlx::query_scalar(&format!(
+        r#"
+        SELECT race FROM {}
+        WHERE season = $1 AND race IS NOT NULL
+        ORDER BY round DESC
+        LIMIT 1
+        "#,
+        kind.table()
+    ))
+    .bind(season)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(race.map(|Json(race)| race))
+}
+
+/// Saves the Jolpica classification of a session if it is final. Returns
+/// whether it was stored.
+async fn store_if_final(db: &PgPool, kind: ResultKind, race: &Race) -> Result<bool, sqlx::Error> {
+    let Ok(round) = race.round.parse::<i32>() else {
+        return Ok(false);
+    };
+    if !kind.has_results(race) || !is_settled(kind.start(race)) {
+        return Ok(false);
+    }
+
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO {table} (season, round, race)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (season, round)
+        DO UPDATE SET race = EXCLUDED.race
+        WHERE {table}.race IS NULL
+        "#,
+        table = kind.table()
+    ))
+    .bind(&race.season)
+    .bind(round)
+    .bind(Json(race))
+    .execute(db)
+    .await?;
+
+    Ok(true)
+}
+
+/// Jolpica classification of a session, `round` can also be `"last"`. Final
+/// results are served from the results tables and only fetched once.
+pub async fn classification(
+    state: &AppState,
+    kind: ResultKind,
+    season: &str,
+    round: &str,
+) -> Result<Option<Race>, Error> {
+    if let Ok(round) = round.parse::<i32>() {
+        if let Some(race) = stored_race(&state.db_pool, kind, season, round).await? {
+            return Ok(Some(race));
+        }
+    }
+
+    match kind.fetch(state, season, round).await {
+        Ok(Some(race)) => {
+            store_if_final(&state.db_pool, kind, &race).await?;
+            Ok(Some(race))
+        }
+        Ok(None) => Ok(None),
+        Err(e) if round == "last" => {
+            warn!("Serving stored results, Jolpica unavailable: {:?}", e);
+            match latest_stored_race(&state.db_pool, kind, season).await? {
+                Some(race) => Ok(Some(race)),
+                None => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct ScheduledSession {
+    season: String,
+    round: String,
+    session_type: String,
+    start: NaiveDateTime,
+}
+
+async fn scheduled_session(
+    db: &PgPool,
+    session_key: i32,
+) -> Result<Option<ScheduledSession>, sqlx::Error> {
+    sqlx::query_as::<_, ScheduledSession>(
+        r#"
+        SELECT
+            r.season,
+            r.round,
+            s."sessionType" AS session_type,
+            s."date" + COALESCE(s."time", TIME '00:00') AS start
+        FROM "Sessions" s
+        JOIN "Races" r ON r.id = s."raceId"
+        WHERE s.session_key = $1
+        "#,
+    )
+    .bind(session_key)
+    .fetch_optional(db)
+    .await
+}
+
+async fn store_session_results(
+    db: &PgPool,
+    kind: ResultKind,
+    season: &str,
+    round: i32,
+    session_key: i32,
+    results: &[SessionResult],
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        r#"
+        INSERT INTO {table} (season, round, session_key, session_results)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (season, round)
+        DO UPDATE SET
+            session_key = EXCLUDED.session_key,
+            session_results = EXCLUDED.session_results
+        WHERE {table}.session_results IS NULL
+        "#,
+        table = kind.table()
+    ))
+    .bind(season)
+    .bind(round)
+    .bind(session_key)
+    .bind(Json(results))
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// OpenF1 classification of a session. Final race, qualifying and sprint
+/// classifications are served from the results tables and only fetched once.
+pub async fn session_results(
+    state: &AppState,
+    session_key: u32,
+) -> Result<Vec<SessionResult>, Error> {
+    let key = session_key as i32;
+    let scheduled = scheduled_session(&state.db_pool, key).await?;
+    let archived = scheduled.and_then(|s| {
+        let kind = ResultKind::from_session_type(&s.session_type)?;
+        let round = s.round.parse::<i32>().ok()?;
+        Some((kind, s.season, round, s.start))
+    });
+
+    if let Some((kind, ..)) = &archived {
+        let stored: Option<Json<Vec<SessionResult>>> = sqlx::query_scalar(&format!(
+            "SELECT session_results FROM {} WHERE session_key = $1 AND session_results IS NOT NULL",
+            kind.table()
+        ))
+        .bind(key)
+        .fetch_optional(&state.db_pool)
+        .await?;
+
+        if let Some(Json(results)) = stored {
+            return Ok(results);
+        }
+    }
+
+    let results = state.openf1.session_result(session_key, None).await?;
+
+    if let Some((kind, season, round, start)) = archived {
+        if !results.is_empty() && is_settled(start) {
+            store_session_results(&state.db_pool, kind, &season, round, key, &results).await?;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Stores the Jolpica and OpenF1 classifications of one session of a round.
+/// Returns whether anything was stored.
+async fn archive_round_results(
+    state: &AppState,
+    kind: ResultKind,
+    season: &str,
+    round: &str,
+    session_key: Option<i32>,
+    has_race: bool,
+) -> Result<bool, Error> {
+    let mut archived = false;
+    if !has_race {
+        if let Some(race) = kind.fetch(state, season, round).await? {
+            archived = store_if_final(&state.db_pool, kind, &race).await?;
+        }
+    }
+    if let Some(session_key) = session_key {
+        archived |= !session_results(state, session_key as u32).await?.is_empty();
+    }
+    Ok(archived)
+}
+
+async fn record_missing(
+    db: &PgPool,
+    kind: ResultKind,
+    season: &str,
+    round: &str,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    warn!(
+        "No {} results for {} round {}: {}",
+        kind.session_type(),
+        season,
+        round,
+        reason
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO "MissingResults" (season, round, session_type, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (season, round, session_type)
+        DO UPDATE SET
+            reason = EXCLUDED.reason,
+            attempts = "MissingResults".attempts + 1,
+            last_attempt_at = now()
+        "#,
+    )
+    .bind(season)
+    .bind(round)
+    .bind(kind.session_type())
+    .bind(reason)
+    .execute(db)
+    .await?;
+
+    Ok(())
+}
+
+/// Stores the Jolpica and OpenF1 classifications of every race, qualifying
+/// and sprint of the season that is final but not saved yet. Sessions that
+/// fail or have no results are recorded in "MissingResults" and skipped
+/// after `MAX_ATTEMPTS`.
+pub async fn archive_final_results(state: &AppState, season: &str) -> Result<usize, Error> {
+    let cutoff = Utc::now().naive_utc() - Duration::hours(RESULTS_SETTLE_HOURS);
+    let mut stored = 0;
+
+    for kind in ResultKind::ALL {
+        let sessions: Vec<(String, Option<i32>, bool)> = sqlx::query_as(&format!(
+            r#"
+            SELECT
+                r.round,
+                s.session_key,
+                t.race IS NOT NULL AS has_race
+            FROM "Races" r
+            JOIN "Sessions" s ON s."raceId" = r.id
+            LEFT JOIN {table} t ON t.season = r.season AND t.round::TEXT = r.round
+            LEFT JOIN "MissingResults" m
+                ON m.season = r.season AND m.round = r.round AND m.session_type = $2
+            WHERE r.season = $1
+            AND s."sessionType" = $2
+            AND s."date" + COALESCE(s."time", TIME '00:00') < $3
+            AND (t.race IS NULL OR (s.session_key IS NOT NULL AND t.session_results IS NULL))
+            AND COALESCE(m.attempts, 0) < $4
+            "#,
+            table = kind.table()
+        ))
+        .bind(season)
+        .bind(kind.session_type())
+        .bind(cutoff)
+        .bind(MAX_ATTEMPTS)
+        .fetch_all(&state.db_pool)
+        .await?;
+
+        for (round, session_key, has_race) in sessions {
+            match archive_round_results(state, kind, season, &round, session_key, has_race).await {
+                Ok(true) => {
+                    info!(
+                        "Archived {} results of {} round {}",
+                        kind.session_type(),
+                        season,
+                        round
+                    );
+                    sqlx::query(
+                        r#"
+                        DELETE FROM "MissingResults"
+                        WHERE season = $1 AND round = $2 AND session_type = $3
+                        "#,
+                    )
+                    .bind(season)
+                    .bind(&round)
+                    .bind(kind.session_type())
+                    .execute(&state.db_pool)
+                    .await?;
+                    stored += 1;
+                }
+                Ok(false) => {
+                    let reason = "No final results published";
+                    record_missing(&state.db_pool, kind, season, &round, reason).await?;
+                }
+                Err(e) => {
+                    record_missing(&state.db_pool, kind, season, &round, &e.to_string()).await?;
+                }
+            }
+        }
+    }
+
+    Ok(stored)
+}
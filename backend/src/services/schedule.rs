@@ -0,0 +1,170 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use http::StatusCode;
+use sqlx::{PgExecutor, PgPool};
+
+use crate::models::{error::Error, fantasy::LockStatus};
+
+/// Session types whose start freezes fantasy team changes for the round.
+pub const LOCKING_SESSIONS: [&str; 2] = ["Qualifying", "SprintQualifying"];
+/// Hours after the scheduled start after which a session is over and its
+/// OpenF1 data complete, leaving room for red flags.
+pub const SESSION_COMPLETE_HOURS: i64 = 6;
+
+/// Round of the season that team changes currently apply to, i.e. the next
+/// race that has not been run yet.
+pub async fn next_round(db: &PgPool, season: &str) -> Result<Option<i32>, sqlx::Error> {
+    let round: Option<String> = sqlx::query_scalar(
+        r#"
+        SELECT round
+        FROM "Races"
+        WHERE season = $1 AND "date" >= $2
+        ORDER BY "date" ASC
+        LIMIT 1
+        "#,
+    )
+    .bind(season)
+    .bind(Utc::now().date_naive())
+    .fetch_optional(db)
+    .await?;
+
+    Ok(round.and_then(|r| r.parse().ok()))
+}
+
+/// Start of the first qualifying-type session of a round, or of the race when
+/// the calendar has no such session. Missing times of day are treated as
+/// midnight UTC. `None` when the race has no date at all, unknown rounds are
+/// a 404.
+pub async fn lock_time<'e, E: PgExecutor<'e>>(
+    db: E,
+    season: &str,
+    round: i32,
+) -> Result<Option<DateTime<Utc>>, Error> {
+    let start: Option<Option<NaiveDateTime>> = sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(
+            s."date" + COALESCE(s."time", TIME '00:00'),
+            r."date" + COALESCE(r."time", TIME '00:00')
+        )
+        FROM "Races" r
+        LEFT JOIN LATERAL (
+            SELECT "date", "time"
+            FROM "Sessions"
+            WHERE "raceId" = r.id
+            AND "sessionType" = ANY($3)
+            AND "date" IS NOT NULL
+            ORDER BY "date" ASC, "time" ASC NULLS FIRST
+            LIMIT 1
+        ) s ON true
+        WHERE r.season = $1
+        AND r.round = $2
+        "#,
+    )
+    .bind(season)
+    .bind(round.to_string())
+    .bind(&LOCKING_SESSIONS[..])
+    .fetch_optional(db)
+    .await?;
+
+    let Some(start) = start else {
+        return Err(Error::new(StatusCode::NOT_FOUND, "Round not found"));
+    };
+    Ok(start.map(|start| start.and_utc()))
+}
+
+pub async fn lock_status<'e, E: PgExecutor<'e>>(
+    db: E,
+    season: &str,
+    round: i32,
+) -> Result<LockStatus, Error> {
+    let lock_time = lock_time(db, season, round).await?;
+    let now = Utc::now();
+
+    Ok(LockStatus {
+        season: season.to_string(),
+        round,
+        lock_time,
+        // Fail closed when the round has no date to lock on
+        locked: lock_time.is_none_or(|t| now >= t),
+        seconds_until_lock: lock_time.map(|t| (t - now).num_seconds().max(0)),
+    })
+}
+
+/// Rejects fantasy changes for a round whose qualifying has already started.
+/// Run it in the transaction making the change, right before committing, so
+/// a change that was started before the lock cannot land after it.
+pub async fn ensure_unlocked<'e, E: PgExecutor<'e>>(
+    db: E,
+    season: &str,
+    round: i32,
+) -> Result<(), Error> {
+    if lock_status(db, season, round).await?.locked {
+        return Err(Error::new(
+            StatusCode::LOCKED,
+            "Team changes are locked until this round is over",
+        ));
+    }
+    Ok(())
+}
+
+/// Season of the most recent race that has been run, falling back to the
+/// latest season in the calendar.
+pub async fn current_season(db: &PgPool) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar(
+        r#"
+        SELECT COALESCE(
+            (SELECT season FROM "Races" WHERE "date" <= $1 ORDER BY "date" DESC LIMIT 1),
+            (SELECT MAX(season) FROM "Races")
+        )
+        "#,
+    )
+    .bind(Utc::now().date_naive())
+    .fetch_one(db)
+    .await
+}
+
+/// Whether the session with this OpenF1 key is over. Sessions missing from
+/// the calendar count as running.
+pub async fn session_completed(db: &PgPool, session_key: u32) -> Result<bool, sqlx::Error> {
+    let cutoff = Utc::now().naive_utc() - Duration::hours(SESSION_COMPLETE_HOURS);
+
+    sqlx::query_scalar(
+        r#"
+        SELECT EXISTS (
+            SELECT 1 FROM "Sessions"
+            WHERE session_key = $1
+            AND "date" + COALESCE("time", TIME '00:00') < $2
+        )
+        "#,
+    )
+    .bind(session_key as i32)
+    .bind(cutoff)
+    .fetch_one(db)
+    .await
+}
+
+/// Session types and OpenF1 keys of the sessions of a round with one of
+/// `session_types`, in running order. Sessions without a key are left out.
+pub async fn round_session_keys(
+    db: &PgPool,
+    season: &str,
+    round: i32,
+    session_types: &[&str],
+) -> Result<Vec<(String, i32)>, sqlx::Error> {
+    sqlx::query_as(
+        r#"
+        SELECT s."sessionType", s.session_key
+        FROM "Sessions" s
+        JOIN "Races" r ON r.id = s."raceId"
+        WHERE r.season = $1
+        AND r.round = $2
+        AND s."sessionType" = ANY($3)
+        AND s.session_key IS NOT NULL
+        ORDER BY s."date" ASC, s."time" ASC NULLS FIRST
+        "#,
+    )
+    .bind(season)
+    .bind(round.to_string())
+    .bind(session_types)
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+
+use crate::{
+    models::{
+        error::Error,
+        fantasy::{
+            FantasyAsset, FantasyTeamRound, ASSET_CONSTRUCTOR, ASSET_DRIVER, CHIP_EXTRA_BOOST,
+            CHIP_LIMITLESS,
+        },
+        jolpica::RaceResult,
+        scoring::{PointsBreakdown, ScoringInput, ScoringRuleSet, ScoringRules, SessionScore},
+    },
+    services::{
+        pricing::update_prices,
+        results::{classification, ResultKind},
+    },
+    utils::state::AppState,
+};
+use http::StatusCode;
+use sqlx::{types::Json, PgPool};
+use tracing::{info, warn};
+
+pub const SCORED_SESSIONS: [&str; 3] = ["Qualifying", "Sprint", "Race"];
+
+/// Applies a rule set to the results of a single session and returns the
+/// points breakdown of every driver, keyed by driver number.
+pub fn score_session(
+    rules: &ScoringRules,
+    results: &[ScoringInput],
+) -> HashMap<String, PointsBreakdown> {
+    let starters = results.len() as i64;
+    let mut scores = HashMap::new();
+
+    for result in results {
+        let mut breakdown = PointsBreakdown::default();
+
+        if result.classified {
+            breakdown.position = (result.position as usize)
+                .checked_sub(1)
+                .and_then(|idx| rules.position_points.get(idx))
+                .copied()
+                .unwrap_or(0.0);
+
+            if let Some(grid) = result.grid {
+                // Grid 0 means a pit lane start
+                let grid = if grid == 0 { starters } else { grid as i64 };
+                let delta = grid - result.position as i64;
+                breakdown.positions_gained = if delta > 0 {
+                    rules.position_gained * delta as f64
+                } else {
+                    rules.position_lost * (-delta) as f64
+                };
+            }
+
+            if result.fastest_lap {
+                breakdown.fastest_lap = rules.fastest_lap;
+            }
+        } else {
+            breakdown.dnf = rules.dnf;
+        }
+
+        let teammate = results.iter().find(|other| {
+            other.constructor_ref == result.constructor_ref
+                && other.driver_number != result.driver_number
+        });
+        if let Some(teammate) = teammate {
+            let beat =
+                result.classified && (!teammate.classified || result.position < teammate.position);
+            if beat {
+                breakdown.beat_teammate = rules.beat_teammate;
+            }
+        }
+
+        scores.insert(result.driver_number.clone(), breakdown);
+    }
+
+    scores
+}
+
+fn race_input(result: RaceResult) -> Option<ScoringInput> {
+    Some(ScoringInput {
+        position: result.position.parse().ok()?,
+        grid: result.grid.and_then(|g| g.parse().ok()),
+        classified: result.position_text.parse::<u32>().is_ok(),
+        fastest_lap: result
+            .fastest_lap
+            .is_some_and(|lap| lap.rank.as_deref() == Some("1")),
+        driver_number: result.number,
+        constructor_ref: result.constructor.constructor_id,
+    })
+}
+
+async fn fetch_session_results(
+    state: &AppState,
+    season: &str,
+    round: i32,
+    session_type: &str,
+) -> Result<Vec<ScoringInput>, Error> {
+    let round = round.to_string();
+
+    let inputs = match session_type {
+        "Qualifying" => classification(state, ResultKind::Qualifying, season, &round)
+            .await?
+            .map(|race| race.qualifying_results)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|r| {
+                Some(ScoringInput {
+                    position: r.position.parse().ok()?,
+                    grid: None,
+                    // Everyone is classified in qualifying
+                    classified: true,
+                    fastest_lap: false,
+                    driver_number: r.number,
+                    constructor_ref: r.constructor.constructor_id,
+                })
+            })
+            .collect(),
+        "Sprint" => classification(state, ResultKind::Sprint, season, &round)
+            .await?
+            .map(|race| race.sprint_results)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(race_input)
+            .collect(),
+        _ => classification(state, ResultKind::Race, season, &round)
+            .await?
+            .map(|race| race.results)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(race_input)
+            .collect(),
+    };
+
+    Ok(inputs)
+}
+
+async fn active_rules(db: &PgPool, session_type: &str) -> Result<Option<ScoringRuleSet>, Error> {
+    let rules = sqlx::query_as::<_, ScoringRuleSet>(
+        r#"
+        SELECT version, rules
+        FROM "FantasyScoringRules"
+        WHERE session_type = $1 AND is_active
+        ORDER BY version DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(session_type)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(rules)
+}
+
+/// Scores every finished session of a round and stores the points of each
+/// driver and constructor asset. Re-scoring a round overwrites its rows.
+pub async fn score_round(
+    state: &AppState,
+    season: &str,
+    round: i32,
+) -> Result<Vec<SessionScore>, Error> {
+    let assets =
+        sqlx::query_as::<_, FantasyAsset>(r#"SELECT * FROM "FantasyAssets" WHERE season = $1"#)
+            .bind(season)
+            .fetch_all(&state.db_pool)
+            .await?;
+
+    if assets.is_empty() {
+        return Err(Error::new(
+            StatusCode::NOT_FOUND,
+            "No fantasy assets for this season",
+        ));
+    }
+
+    let mut summary = Vec::new();
+
+    for session_type in SCORED_SESSIONS {
+        let results = fetch_session_results(state, season, round, session_type).await?;
+        if results.is_empty() {
+            info!("No {} results for {} round {}", session_type, season, round);
+            continue;
+        }
+
+        let Some(rule_set) = active_rules(&state.db_pool, session_type).await? else {
+            warn!("No active scoring rules for {}", session_type);
+            continue;
+        };
+
+        let driver_scores = score_session(&rule_set.rules, &results);
+
+        let mut constructor_scores: HashMap<&str, PointsBreakdown> = HashMap::new();
+        for result in &results {
+            if let Some(breakdown) = driver_scores.get(&result.driver_number) {
+                constructor_scores
+                    .entry(result.constructor_ref.as_str())
+                    .or_default()
+                    .add(breakdown);
+            }
+        }
+
+        let mut rows = Vec::new();
+        for asset in &assets {
+            let breakdown = match asset.asset_type.as_str() {
+                ASSET_DRIVER => driver_scores.get(&asset.external_ref),
+                ASSET_CONSTRUCTOR => constructor_scores.get(asset.external_ref.as_str()),
+                _ => None,
+            };
+            if let Some(breakdown) = breakdown {
+                rows.push((asset.id, breakdown.clone()));
+            }
+        }
+
+        let mut tx = state.db_pool.begin().await?;
+        for (asset_id, breakdown) in &rows {
+            sqlx::query(
+                r#"
+                INSERT INTO "FantasyPoints"
+                    (season, round, session_type, asset_id, points, breakdown, rules_version)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (season, round, session_type, asset_id)
+                DO UPDATE SET
+                    points = EXCLUDED.points,
+                    breakdown = EXCLUDED.breakdown,
+                    rules_version = EXCLUDED.rules_version,
+                    created_at = now()
+                "#,
+            )
+            .bind(season)
+            .bind(round)
+            .bind(session_type)
+            .bind(asset_id)
+            .bind(breakdown.total())
+            .bind(Json(breakdown))
+            .bind(rule_set.version)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        info!(
+            "Scored {} assets for {} {} round {}",
+            rows.len(),
+            season,
+            session_type,
+            round
+        );
+        summary.push(SessionScore {
+            session_type: session_type.to_string(),
+            rules_version: rule_set.version,
+            assets_scored: rows.len(),
+        });
+    }
+
+    if !summary.is_empty() {
+        let teams = score_teams(state, season, round).await?;
+        info!("Scored {} teams for {} round {}", teams, season, round);
+
+        let priced = update_prices(state, season, round).await?;
+        info!("Updated {} prices for {} round {}", priced, season, round);
+    }
+
+    Ok(summary)
+}
+
+/// Picks of every team of the season that count for `round`. Limitless
+/// picks only apply to their own round.
+pub async fn picks_for_round(
+    db: &PgPool,
+    season: &str,
+    round: i32,
+) -> Result<Vec<FantasyTeamRound>, Error> {
+    let picks = sqlx::query_as::<_, FantasyTeamRound>(
+        r#"
+        SELECT DISTINCT ON (r.team_id) r.*
+        FROM "FantasyTeamRounds" r
+        JOIN "FantasyTeams" t ON t.id = r.team_id
+        WHERE t.season = $1
+        AND r.round <= $2
+        AND NOT (r.chip IS NOT DISTINCT FROM $3 AND r.round < $2)
+        ORDER BY r.team_id, r.round DESC
+        "#,
+    )
+    .bind(season)
+    .bind(round)
+    .bind(CHIP_LIMITLESS)
+    .fetch_all(db)
+    .await?;
+
+    Ok(picks)
+}
+
+/// Totals the points of every team of the season for a scored round, using
+/// the picks that were in place for that round.
+pub async fn score_teams(state: &AppState, season: &str, round: i32) -> Result<usize, Error> {
+    let picks = picks_for_round(&state.db_pool, season, round).await?;
+
+    let asset_points: HashMap<i32, f64> = sqlx::query_as::<_, (i32, f64)>(
+        r#"
+        SELECT asset_id, SUM(points)
+        FROM "FantasyPoints"
+        WHERE season = $1 AND round = $2
+        GROUP BY asset_id
+        "#,
+    )
+    .bind(season)
+    .bind(round)
+    .fetch_all(&state.db_pool)
+    .await?
+    .into_iter()
+    .collect();
+
+    let mut tx = state.db_pool.begin().await?;
+    for team in &picks {
+        let mut points: f64 = team
+            .driver_ids
+            .iter()
+            .chain(team.constructor_ids.iter())
+            .filter_map(|id| asset_points.get(id))
+            .sum();
+        // Penalties and chips only count in the round they were made for
+        if team.round == round {
+            points -= team.penalty_points;
+            if team.chip.as_deref() == Some(CHIP_EXTRA_BOOST) {
+                let boosted = team
+                    .boost_asset_id
+                    .and_then(|id| asset_points.get(&id))
+                    .unwrap_or(&0.0);
+                // The boosted driver scores triple, once is already counted
+                points += boosted * 2.0;
+            }
+        }
+
+        sqlx::query(
+            r#"
+            INSERT INTO "FantasyTeamScores" (team_id, season, round, points)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (team_id, round)
+            DO UPDATE SET points = EXCLUDED.points, updated_at = now()
+            "#,
+        )
+        .bind(team.team_id)
+        .bind(season)
+        .bind(round)
+        .bind(points)
+        .execute(&mut *tx)
+        .await?;
+    }
+    tx.commit().await?;
+
+    Ok(picks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> ScoringRules {
+        ScoringRules {
+            position_points: vec![25.0, 18.0, 15.0, 12.0, 10.0],
+            position_gained: 1.0,
+            position_lost: -1.0,
+            fastest_lap: 5.0,
+            dnf: -10.0,
+            beat_teammate: 2.0,
+        }
+    }
+
+    fn input(driver: &str, team: &str, position: u32, grid: Option<u32>) -> ScoringInput {
+        ScoringInput {
+            driver_number: driver.to_string(),
+            constructor_ref: team.to_string(),
+            position,
+            grid,
+            classified: true,
+            fastest_lap: false,
+        }
+    }
+
+    #[test]
+    fn scores_position_and_positions_gained() {
+        let results = [
+            input("1", "red_bull", 1, Some(3)),
+            input("16", "ferrari", 2, Some(1)),
+        ];
+        let scores = score_session(&rules(), &results);
+
+        let winner = &scores["1"];
+        assert_eq!(winner.position, 25.0);
+        assert_eq!(winner.positions_gained, 2.0);
+
+        let second = &scores["16"];
+        assert_eq!(second.position, 18.0);
+        assert_eq!(second.positions_gained, -1.0);
+    }
+
+    #[test]
+    fn positions_outside_the_table_score_nothing() {
+        let results = [input("2", "williams", 6, Some(6))];
+        let scores = score_session(&rules(), &results);
+
+        assert_eq!(scores["2"].position, 0.0);
+        assert_eq!(scores["2"].positions_gained, 0.0);
+    }
+
+    #[test]
+    fn pit_lane_start_counts_from_the_back() {
+        let results = [
+            input("1", "red_bull", 1, Some(1)),
+            input("44", "mercedes", 2, Some(2)),
+            input("4", "mclaren", 3, Some(0)),
+        ];
+        let scores = score_session(&rules(), &results);
+
+        // Started from the pit lane behind all 3 starters, gained nothing
+        assert_eq!(scores["4"].positions_gained, 0.0);
+
+        let results = [
+            input("4", "mclaren", 1, Some(0)),
+            input("1", "red_bull", 2, Some(1)),
+            input("44", "mercedes", 3, Some(2)),
+        ];
+        let scores = score_session(&rules(), &results);
+        assert_eq!(scores["4"].positions_gained, 2.0);
+    }
+
+    #[test]
+    fn unclassified_drivers_get_the_dnf_penalty_only() {
+        let mut retired = input("14", "aston_martin", 19, Some(2));
+        retired.classified = false;
+        retired.fastest_lap = true;
+        let scores = score_session(&rules(), &[retired]);
+
+        let breakdown = &scores["14"];
+        assert_eq!(breakdown.dnf, -10.0);
+        assert_eq!(breakdown.position, 0.0);
+        assert_eq!(breakdown.positions_gained, 0.0);
+        assert_eq!(breakdown.fastest_lap, 0.0);
+        assert_eq!(breakdown.total(), -10.0);
+    }
+
+    #[test]
+    fn disqualified_results_are_not_classified() {
+        let result: RaceResult = serde_json::from_value(serde_json::json!({
+            "number": "44",
+            "position": "2",
+            "positionText": "D",
+            "points": "0",
+            "grid": "3",
+            "Driver": { "driverId": "hamilton", "givenName": "Lewis", "familyName": "Hamilton" },
+            "Constructor": { "constructorId": "ferrari", "name": "Ferrari" }
+        }))
+        .unwrap();
+
+        let input = race_input(result).unwrap();
+        assert!(!input.classified);
+        assert_eq!(score_session(&rules(), &[input])["44"].dnf, -10.0);
+    }
+
+    #[test]
+    fn fastest_lap_bonus() {
+        let mut fastest = input("81", "mclaren", 4, None);
+        fastest.fastest_lap = true;
+        let scores = score_session(&rules(), &[fastest, input("1", "red_bull", 1, None)]);
+
+        assert_eq!(scores["81"].fastest_lap, 5.0);
+        assert_eq!(scores["1"].fastest_lap, 0.0);
+    }
+
+    #[test]
+    fn teammate_comparison() {
+        let mut retired = input("63", "mercedes", 20, None);
+        retired.classified = false;
+        let results = [
+            input("16", "ferrari", 3, None),
+            input("44", "ferrari", 5, None),
+            input("12", "mercedes", 4, None),
+            retired,
+            input("23", "williams", 6, None),
+        ];
+        let scores = score_session(&rules(), &results);
+
+        assert_eq!(scores["16"].beat_teammate, 2.0);
+        assert_eq!(scores["44"].beat_teammate, 0.0);
+        // Finishing beats a retired teammate
+        assert_eq!(scores["12"].beat_teammate, 2.0);
+        assert_eq!(scores["63"].beat_teammate, 0.0);
+        // No teammate, no bonus
+        assert_eq!(scores["23"].beat_teammate, 0.0);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::{Duration as ChronoDuration, Utc};
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info, warn};
+
+use crate::{
+    clients::openf1::OpenF1Error,
+    models::{
+        error::Error,
+        openf1::SessionInfo,
+        session::{PendingSession, SessionKeyReport},
+    },
+    utils::{race_utils::map_session_name, state::AppState},
+};
+
+/// How long after the scheduled start a session is first looked up, giving
+/// OpenF1 time to publish it.
+pub const RESOLVE_DELAY_MINUTES: i64 = 5;
+/// Failed lookups after which a session is left to operators.
+pub const MAX_ATTEMPTS: i32 = 24;
+/// OpenF1 sessions with an unknown name still match when they start this
+/// close to the scheduled time.
+const START_TOLERANCE_MINUTES: i64 = 60;
+
+/// Runs `reconcile_session_keys` every `SESSION_KEY_SYNC_INTERVAL_SECS`.
+pub fn spawn_session_key_reconciler(state: Arc<AppState>) {
+    let secs = state.config.session_key_sync_interval_secs;
+    if secs == 0 {
+        info!("Session key reconciler disabled");
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(secs));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            match reconcile_session_keys(&state).await {
+                Ok(report) if report.resolved + report.unresolved > 0 => info!(
+                    "Session keys: {} resolved, {} unresolved",
+                    report.resolved, report.unresolved
+                ),
+                Ok(_) => {}
+                Err(e) => error!("Session key reconciler failed: {:?}", e),
+            }
+        }
+    });
+}
+
+/// Picks the OpenF1 session for a scheduled session, by name first and by
+/// start time for names `map_session_name` does not know. Sessions whose key
+/// is in `used` already belong to another session of the race.
+fn match_session<'a>(
+    pending: &PendingSession,
+    candidates: &'a [SessionInfo],
+    used: &HashSet<i32>,
+) -> Option<&'a SessionInfo> {
+    let start = pending.start.and_utc();
+    let distance = |s: &SessionInfo| (s.date_start - start).num_minutes().abs();
+
+    candidates
+        .iter()
+        .filter(|s| !used.contains(&s.session_key))
+        .filter(|s| match map_session_name(&s.session_name) {
+            Some(name) => name == pending.session_type,
+            None => distance(s) <= START_TOLERANCE_MINUTES,
+        })
+        .min_by_key(|s| distance(s))
+}
+
+async fn fetch_openf1_sessions(
+    state: &AppState,
+    sessions: &[PendingSession],
+) -> Result<Vec<SessionInfo>, OpenF1Error> {
+    let (Some(first), Some(last)) = (sessions.first(), sessions.last()) else {
+        return Ok(Vec::new());
+    };
+    let from = first.start.date() - ChronoDuration::days(1);
+    let to = last.start.date() + ChronoDuration::days(1);
+
+    let found = state.openf1.sessions(&first.season, from, to).await?;
+
+    for session in &found {
+        if map_session_name(&session.session_name).is_none() {
+            warn!(
+                "Unknown OpenF1 session name '{}' (session_key {})",
+                session.session_name, session.session_key
+            );
+        }
+    }
+
+    Ok(found)
+}
+
+async fn pending_sessions(state: &AppState) -> Result<Vec<PendingSession>, sqlx::Error> {
+    let cutoff = Utc::now().naive_utc() - ChronoDuration::minutes(RESOLVE_DELAY_MINUTES);
+
+    sqlx::query_as::<_, PendingSession>(
+        r#"
+        SELECT
+            s.id,
+            r.id AS race_id,
+            r.season,
+            s."sessionType" AS session_type,
+            s."date" + COALESCE(s."time", TIME '00:00') AS start
+        FROM "Sessions" s
+        JOIN "Races" r ON r.id = s."raceId"
+        LEFT JOIN "UnresolvedSessions" u ON u.session_id = s.id
+        WHERE s.session_key IS NULL
+        AND s."date" IS NOT NULL
+        AND s."date" + COALESCE(s."time", TIME '00:00') <= $1
+        AND COALESCE(u.attempts, 0) < $2
+        ORDER BY start ASC
+        "#,
+    )
+    .bind(cutoff)
+    .bind(MAX_ATTEMPTS)
+    .fetch_all(&state.db_pool)
+    .await
+}
+
+async fn record_unresolved(
+    state: &AppState,
+    session: &PendingSession,
+    reason: &str,
+) -> Result<(), sqlx::Error> {
+    warn!(
+        "Could not resolve session_key for {} of race {}: {}",
+        session.session_type, session.race_id, reason
+    );
+
+    sqlx::query(
+        r#"
+        INSERT INTO "UnresolvedSessions" (session_id, race_id, session_type, reason)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (session_id)
+        DO UPDATE SET
+            reason = EXCLUDED.reason,
+            attempts = "UnresolvedSessions".attempts + 1,
+            last_attempt_at = now()
+        "#,
+    )
+    .bind(session.id)
+    .bind(session.race_id)
+    .bind(&session.session_type)
+    .bind(reason)
+    .execute(&state.db_pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Fills in session_key and meeting_key for every session that has started
+/// without one. Sessions that cannot be matched end up in
+/// "UnresolvedSessions".
+pub async fn reconcile_session_keys(state: &AppState) -> Result<SessionKeyReport, Error> {
+    let mut by_race: BTreeMap<i64, Vec<PendingSession>> = BTreeMap::new();
+    for session in pending_sessions(state).await? {
+        by_race.entry(session.race_id).or_default().push(session);
+    }
+
+    let mut report = SessionKeyReport::default();
+    for (race_id, sessions) in &by_race {
+        let candidates = match fetch_openf1_sessions(state, sessions).await {
+            Ok(candidates) => candidates,
+            Err(e) => {
+                warn!(
+                    "Fetching OpenF1 sessions for race {} failed: {}",
+                    race_id, e
+                );
+                for session in sessions {
+                    record_unresolved(state, session, &e.to_string()).await?;
+                    report.unresolved += 1;
+                }
+                continue;
+            }
+        };
+
+        // Keys of sessions of the race resolved earlier or in this run
+        let mut used: HashSet<i32> = sqlx::query_scalar::<_, i32>(
+            r#"SELECT session_key FROM "Sessions" WHERE "raceId" = $1 AND session_key IS NOT NULL"#,
+        )
+        .bind(race_id)
+        .fetch_all(&state.db_pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        for session in sessions {
+            let Some(found) = match_session(session, &candidates, &used) else {
+                record_unresolved(state, session, "No matching OpenF1 session").await?;
+                report.unresolved += 1;
+                continue;
+            };
+
+            info!(
+                "Resolved {} of race {} to session_key {}, meeting_key {}",
+                session.session_type, session.race_id, found.session_key, found.meeting_key
+            );
+
+            sqlx::query(
+                r#"UPDATE "Sessions" SET "session_key" = $1, "meeting_key" = $2 WHERE id = $3"#,
+            )
+            .bind(found.session_key)
+            .bind(found.meeting_key)
+            .bind(session.id)
+            .execute(&state.db_pool)
+            .await?;
+
+            sqlx::query(r#"DELETE FROM "UnresolvedSessions" WHERE session_id = $1"#)
+                .bind(session.id)
+                .execute(&state.db_pool)
+                .await?;
+
+            used.insert(found.session_key);
+            report.resolved += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, NaiveDateTime};
+
+    use super::*;
+
+    fn pending(session_type: &str, start: &str) -> PendingSession {
+        PendingSession {
+            id: 1,
+            race_id: 1,
+            season: "2025".to_string(),
+            session_type: session_type.to_string(),
+            start: NaiveDateTime::parse_from_str(start, "%Y-%m-%d %H:%M").unwrap(),
+        }
+    }
+
+    fn openf1(session_key: i32, session_name: &str, start: &str) -> SessionInfo {
+        SessionInfo {
+            session_key,
+            meeting_key: 1,
+            session_name: session_name.to_string(),
+            session_type: None,
+            date_start: DateTime::parse_from_rfc3339(start).unwrap().to_utc(),
+            date_end: None,
+            year: Some(2025),
+        }
+    }
+
+    #[test]
+    fn matches_by_name_before_start_time() {
+        let candidates = [
+            openf1(10, "Practice 1", "2025-03-14T01:30:00Z"),
+            openf1(11, "Qualifying", "2025-03-15T05:00:00Z"),
+        ];
+        let found = match_session(
+            &pending("Qualifying", "2025-03-14 01:30"),
+            &candidates,
+            &HashSet::new(),
+        );
+        assert_eq!(found.map(|s| s.session_key), Some(11));
+    }
+
+    #[test]
+    fn unknown_names_match_within_the_start_tolerance() {
+        let candidates = [openf1(12, "Day 1", "2025-03-14T02:00:00Z")];
+        let near = pending("FirstPractice", "2025-03-14 01:30");
+        let far = pending("FirstPractice", "2025-03-14 04:00");
+
+        assert!(match_session(&near, &candidates, &HashSet::new()).is_some());
+        assert!(match_session(&far, &candidates, &HashSet::new()).is_none());
+    }
+
+    #[test]
+    fn used_keys_are_not_matched_again() {
+        let candidates = [openf1(12, "Day 1", "2025-03-14T02:00:00Z")];
+        let session = pending("FirstPractice", "2025-03-14 01:30");
+
+        assert!(match_session(&session, &candidates, &HashSet::from([12])).is_none());
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::{DateTime, Utc};
+
+use crate::{
+    models::{
+        error::Error,
+        openf1::{Lap, PitStop, Position, SessionDriver, Stint},
+        strategy::{
+            DriverStints, DriverStrategy, PitAnalysis, PitStopSummary, PitSwap, SessionStints,
+            StintSummary, SwapKind, TeamStrategy,
+        },
+    },
+    services::archive,
+    utils::state::AppState,
+};
+
+/// Compound reported for a stint OpenF1 has no compound for.
+pub const UNKNOWN_COMPOUND: &str = "UNKNOWN";
+/// Most laps between two stops that can still be an undercut or an overcut.
+const SWAP_WINDOW_LAPS: u32 = 1;
+/// Laps after the later stop before positions are compared again, so both
+/// cars have completed their out-lap.
+const SWAP_SETTLE_LAPS: u32 = 2;
+
+fn summarize(stint: &Stint) -> StintSummary {
+    StintSummary {
+        stint_number: stint.stint_number,
+        compound: stint.compound.clone(),
+        lap_start: stint.lap_start,
+        lap_end: stint.lap_end,
+        laps: match (stint.lap_start, stint.lap_end) {
+            (Some(start), Some(end)) if end >= start => Some(end - start + 1),
+            _ => None,
+        },
+        tyre_age_at_start: stint.tyre_age_at_start,
+    }
+}
+
+/// Stints of every driver, ordered by driver number and stint number.
+pub fn driver_stints(stints: &[Stint], drivers: &[SessionDriver]) -> Vec<DriverStints> {
+    let mut by_driver: BTreeMap<u32, Vec<&Stint>> = BTreeMap::new();
+    for stint in stints {
+        by_driver
+            .entry(stint.driver_number)
+            .or_default()
+            .push(stint);
+    }
+
+    by_driver
+        .into_iter()
+        .map(|(driver_number, mut stints)| {
+            stints.sort_by_key(|s| s.stint_number);
+            let driver = drivers.iter().find(|d| d.driver_number == driver_number);
+            DriverStints {
+                driver_number,
+                name_acronym: driver.and_then(|d| d.name_acronym.clone()),
+                team_name: driver.and_then(|d| d.team_name.clone()),
+                stints: stints.into_iter().map(summarize).collect(),
+            }
+        })
+        .collect()
+}
+
+/// Groups the drivers by team. Drivers without a known team are left out.
+pub fn team_strategies(drivers: &[DriverStints]) -> Vec<TeamStrategy> {
+    let mut teams: BTreeMap<&str, Vec<DriverStrategy>> = BTreeMap::new();
+    for driver in drivers {
+        let Some(team_name) = driver.team_name.as_deref() else {
+            continue;
+        };
+        teams.entry(team_name).or_default().push(DriverStrategy {
+            driver_number: driver.driver_number,
+            stops: driver.stints.len().saturating_sub(1) as u32,
+            compounds: driver
+                .stints
+                .iter()
+                .map(|s| {
+                    s.compound
+                        .as_deref()
+                        .unwrap_or(UNKNOWN_COMPOUND)
+                        .to_string()
+                })
+                .collect(),
+        });
+    }
+
+    teams
+        .into_iter()
+        .map(|(team_name, drivers)| TeamStrategy {
+            team_name: team_name.to_string(),
+            split_strategy: drivers.windows(2).any(|w| w[0].compounds != w[1].compounds),
+            drivers,
+        })
+        .collect()
+}
+
+pub async fn session_stints(state: &AppState, session_key: u32) -> Result<SessionStints, Error> {
+    let stints = archive::stints(state, session_key).await?;
+    let drivers = archive::drivers(state, session_key).await?;
+
+    let drivers = driver_stints(&stints, &drivers);
+    Ok(SessionStints {
+        session_key,
+        teams: team_strategies(&drivers),
+        drivers,
+    })
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// Position of a driver at `at`, from the latest position sample before it.
+/// `positions` must be sorted by date.
+fn position_at(positions: &[&Position], at: DateTime<Utc>) -> Option<u32> {
+    let idx = positions.partition_point(|p| p.date <= at);
+    idx.checked_sub(1).map(|i| positions[i].position)
+}
+
+/// Pairs of drivers who stopped within `SWAP_WINDOW_LAPS` of each other and
+/// swapped places across the stops. Positions are taken at the start of the
+/// earlier stopper's in-lap and `SWAP_SETTLE_LAPS` after the later stop.
+pub fn detect_swaps(pits: &[PitStop], laps: &[Lap], positions: &[Position]) -> Vec<PitSwap> {
+    let lap_starts: HashMap<(u32, u32), DateTime<Utc>> = laps
+        .iter()
+        .filter_map(|l| Some(((l.driver_number, l.lap_number), l.date_start?)))
+        .collect();
+    let mut by_driver: HashMap<u32, Vec<&Position>> = HashMap::new();
+    for p in positions {
+        by_driver.entry(p.driver_number).or_default().push(p);
+    }
+    for samples in by_driver.values_mut() {
+        samples.sort_by_key(|p| p.date);
+    }
+    let position = |driver: u32, at: DateTime<Utc>| {
+        by_driver
+            .get(&driver)
+            .and_then(|samples| position_at(samples, at))
+    };
+
+    let mut swaps = Vec::new();
+    for first in pits {
+        for second in pits {
+            if first.driver_number == second.driver_number
+                || second.lap_number <= first.lap_number
+                || second.lap_number - first.lap_number > SWAP_WINDOW_LAPS
+            {
+                continue;
+            }
+
+            let Some(&before) = lap_starts.get(&(first.driver_number, first.lap_number)) else {
+                continue;
+            };
+            let settled = second.lap_number + SWAP_SETTLE_LAPS;
+            let Some(&after) = lap_starts.get(&(second.driver_number, settled)) else {
+                continue;
+            };
+
+            let (Some(first_before), Some(second_before), Some(first_after), Some(second_after)) = (
+                position(first.driver_number, before),
+                position(second.driver_number, before),
+                position(first.driver_number, after),
+                position(second.driver_number, after),
+            ) else {
+                continue;
+            };
+
+            let swap = if first_before > second_before && first_after < second_after {
+                (SwapKind::Undercut, first, second, first_before, first_after)
+            } else if second_before > first_before && second_after < first_after {
+                (
+                    SwapKind::Overcut,
+                    second,
+                    first,
+                    second_before,
+                    second_after,
+                )
+            } else {
+                continue;
+            };
+            let (kind, gained, lost, position_before, position_after) = swap;
+            swaps.push(PitSwap {
+                kind,
+                gained_by: gained.driver_number,
+                lost_by: lost.driver_number,
+                gained_by_lap: gained.lap_number,
+                lost_by_lap: lost.lap_number,
+                position_before,
+                position_after,
+            });
+        }
+    }
+    swaps.sort_by_key(|s| (s.gained_by_lap.min(s.lost_by_lap), s.position_after));
+    swaps
+}
+
+pub fn pit_stops(
+    pits: &[PitStop],
+    drivers: &[SessionDriver],
+) -> (Option<f64>, Vec<PitStopSummary>) {
+    let median_lane = median(pits.iter().filter_map(|p| p.pit_duration).collect());
+
+    let mut stops: Vec<PitStopSummary> = pits
+        .iter()
+        .map(|p| {
+            let driver = drivers.iter().find(|d| d.driver_number == p.driver_number);
+            PitStopSummary {
+                driver_number: p.driver_number,
+                name_acronym: driver.and_then(|d| d.name_acronym.clone()),
+                team_name: driver.and_then(|d| d.team_name.clone()),
+                lap_number: p.lap_number,
+                date: p.date,
+                lane_duration: p.pit_duration,
+                stop_duration: p.stop_duration,
+                delta_to_median: p.pit_duration.zip(median_lane).map(|(d, m)| d - m),
+            }
+        })
+        .collect();
+    stops.sort_by_key(|s| (s.date, s.driver_number));
+
+    (median_lane, stops)
+}
+
+/// Quickest stop of each team by lane duration. Stops without a duration and
+/// drivers without a known team are left out.
+pub fn fastest_by_team(stops: &[PitStopSummary]) -> Vec<PitStopSummary> {
+    let mut fastest: BTreeMap<&str, &PitStopSummary> = BTreeMap::new();
+    for stop in stops {
+        let (Some(team), Some(lane)) = (stop.team_name.as_deref(), stop.lane_duration) else {
+            continue;
+        };
+        let quicker = fastest
+            .get(team)
+            .and_then(|best| best.lane_duration)
+            .is_none_or(|best| lane < best);
+        if quicker {
+            fastest.insert(team, stop);
+        }
+    }
+
+    let mut fastest: Vec<PitStopSummary> = fastest.into_values().cloned().collect();
+    fastest.sort_by(|a, b| {
+        a.lane_duration
+            .unwrap_or(f64::MAX)
+            .total_cmp(&b.lane_duration.unwrap_or(f64::MAX))
+    });
+    fastest
+}
+
+pub async fn pit_analysis(state: &AppState, session_key: u32) -> Result<PitAnalysis, Error> {
+    let pits = archive::pit(state, session_key).await?;
+    let drivers = archive::drivers(state, session_key).await?;
+    let laps = archive::laps(state, session_key, None).await?;
+    let positions = archive::position(state, session_key).await?;
+
+    let (median_lane_duration, stops) = pit_stops(&pits, &drivers);
+    Ok(PitAnalysis {
+        session_key,
+        median_lane_duration,
+        fastest_by_team: fastest_by_team(&stops),
+        swaps: detect_swaps(&pits, &laps, &positions),
+        stops,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(1_700_000_000 + secs, 0).unwrap()
+    }
+
+    fn pit(driver_number: u32, lap_number: u32) -> PitStop {
+        PitStop {
+            date: at(lap_number as i64 * 90),
+            driver_number,
+            lap_number,
+            pit_duration: Some(22.0),
+            stop_duration: None,
+        }
+    }
+
+    /// Laps 1 to `last_lap` of drivers 1 and 2, 90 seconds each.
+    fn laps(last_lap: u32) -> Vec<Lap> {
+        let lap = |driver_number, lap_number: u32| Lap {
+            session_key: 1,
+            driver_number,
+            lap_number,
+            date_start: Some(at(lap_number as i64 * 90)),
+            lap_duration: Some(90.0),
+            duration_sector_1: None,
+            duration_sector_2: None,
+            duration_sector_3: None,
+            i1_speed: None,
+            i2_speed: None,
+            st_speed: None,
+            is_pit_out_lap: Some(false),
+        };
+        (1..=last_lap)
+            .flat_map(|n| [lap(1, n), lap(2, n)])
+            .collect()
+    }
+
+    /// Positions of drivers 1 and 2 from the start, and from lap 12 on.
+    fn positions(before: [u32; 2], after: [u32; 2]) -> Vec<Position> {
+        let position = |secs, driver_number, position| Position {
+            date: at(secs),
+            driver_number,
+            position,
+        };
+        vec![
+            position(0, 1, before[0]),
+            position(0, 2, before[1]),
+            position(12 * 90, 1, after[0]),
+            position(12 * 90, 2, after[1]),
+        ]
+    }
+
+    #[test]
+    fn swaps_across_stops() {
+        // (case, laps drivers 1 and 2 stopped, positions before and after,
+        // last lap, swap as (kind, gained by, lost by, before, after))
+        let cases = [
+            (
+                "undercut",
+                [10, 11],
+                [2, 1],
+                [1, 2],
+                20,
+                Some((SwapKind::Undercut, 1, 2, 2, 1)),
+            ),
+            (
+                "overcut",
+                [10, 11],
+                [1, 2],
+                [2, 1],
+                20,
+                Some((SwapKind::Overcut, 2, 1, 2, 1)),
+            ),
+            ("no swap", [10, 11], [2, 1], [2, 1], 20, None),
+            ("same lap", [10, 10], [2, 1], [1, 2], 20, None),
+            ("too far apart", [9, 11], [2, 1], [1, 2], 20, None),
+            // Driver 2 stopped on lap 11 and settles on lap 13
+            ("missing settle lap", [10, 11], [2, 1], [1, 2], 12, None),
+        ];
+
+        for (case, stops, before, after, last_lap, expected) in cases {
+            let pits = [pit(1, stops[0]), pit(2, stops[1])];
+            let swaps: Vec<_> = detect_swaps(&pits, &laps(last_lap), &positions(before, after))
+                .into_iter()
+                .map(|s| {
+                    (
+                        s.kind,
+                        s.gained_by,
+                        s.lost_by,
+                        s.position_before,
+                        s.position_after,
+                    )
+                })
+                .collect();
+            assert_eq!(swaps, expected.into_iter().collect::<Vec<_>>(), "{case}");
+        }
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        let cases: [(&[f64], Option<f64>); 4] = [
+            (&[], None),
+            (&[3.0], Some(3.0)),
+            (&[3.0, 1.0, 2.0], Some(2.0)),
+            (&[4.0, 1.0, 3.0, 2.0], Some(2.5)),
+        ];
+        for (values, expected) in cases {
+            assert_eq!(median(values.to_vec()), expected, "{values:?}");
+        }
+    }
+
+    #[test]
+    fn fastest_stop_of_each_team() {
+        let stop = |driver_number, team: Option<&str>, lane| PitStopSummary {
+            driver_number,
+            name_acronym: None,
+            team_name: team.map(str::to_string),
+            lap_number: 10,
+            date: at(900),
+            lane_duration: lane,
+            stop_duration: None,
+            delta_to_median: None,
+        };
+        let stops = [
+            stop(1, Some("Ferrari"), Some(22.0)),
+            stop(16, Some("Ferrari"), Some(21.5)),
+            stop(4, Some("McLaren"), None),
+            stop(81, Some("McLaren"), Some(23.0)),
+            // No team, quickest of all
+            stop(99, None, Some(20.0)),
+        ];
+
+        let fastest: Vec<_> = fastest_by_team(&stops)
+            .into_iter()
+            .map(|s| (s.driver_number, s.lane_duration))
+            .collect();
+        assert_eq!(fastest, vec![(16, Some(21.5)), (81, Some(23.0))]);
+    }
+}
@@ -0,0 +1,623 @@
+use chrono::{DateTime, Duration, Utc};
+use http::StatusCode;
+
+use crate::{
+    models::{
+        error::Error,
+        openf1::{CarData, Lap, Location},
+        telemetry::{
+            Channel, DeltaPoint, LapInfo, LapQuery, LapTrace, Minisector, MinisectorTime,
+            TelemetrySample, TracePoint,
+        },
+    },
+    services::archive,
+    utils::state::AppState,
+};
+
+/// OpenF1 locations are in decimetres.
+const LOCATION_UNITS_PER_METRE: f64 = 10.0;
+
+/// Laps slower than this share of the driver's fastest lap are in-laps, cool
+/// down laps or laps behind the safety car.
+const REPRESENTATIVE_LAP_RATIO: f64 = 1.07;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LapSelection {
+    Latest,
+    Number(u32),
+    Fastest,
+    FastestInSegment(u32),
+}
+
+impl LapSelection {
+    pub fn from_query(query: &LapQuery) -> Result<Self, Error> {
+        let segment = match query.segment.as_deref() {
+            Some(segment) => Some(parse_segment(segment).ok_or_else(|| {
+                Error::new(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Unknown qualifying segment: {}", segment),
+                )
+            })?),
+            None => None,
+        };
+        let fastest = query.fastest.unwrap_or(false);
+
+        match (query.lap, fastest, segment) {
+            (None, false, None) => Ok(LapSelection::Latest),
+            (Some(lap), false, None) => Ok(LapSelection::Number(lap)),
+            (None, true, None) => Ok(LapSelection::Fastest),
+            (None, _, Some(segment)) => Ok(LapSelection::FastestInSegment(segment)),
+            _ => Err(Error::new(
+                StatusCode::BAD_REQUEST,
+                "Choose only one of lap, fastest and segment",
+            )),
+        }
+    }
+
+    pub fn cache_key(&self) -> String {
+        match self {
+            LapSelection::Latest => "latest".to_string(),
+            LapSelection::Number(lap) => format!("lap{}", lap),
+            LapSelection::Fastest => "fastest".to_string(),
+            LapSelection::FastestInSegment(segment) => format!("q{}", segment),
+        }
+    }
+}
+
+fn parse_segment(segment: &str) -> Option<u32> {
+    let segment = segment.trim().to_ascii_lowercase();
+    let number = segment
+        .strip_prefix("sq")
+        .or_else(|| segment.strip_prefix('q'))
+        .unwrap_or(&segment);
+    match number.parse() {
+        Ok(n @ 1..=3) => Some(n),
+        _ => None,
+    }
+}
+
+/// Start of every qualifying segment, taken from the first race control
+/// message of each phase.
+async fn segment_starts(
+    state: &AppState,
+    session_key: u32,
+) -> Result<Vec<(u32, DateTime<Utc>)>, Error> {
+    let mut starts: Vec<(u32, DateTime<Utc>)> = Vec::new();
+    for msg in archive::race_control(state, session_key).await? {
+        let Some(phase) = msg.qualifying_phase else {
+            continue;
+        };
+        match starts.iter_mut().find(|(p, _)| *p == phase) {
+            Some((_, start)) => *start = (*start).min(msg.date),
+            None => starts.push((phase, msg.date)),
+        }
+    }
+    starts.sort_by_key(|(_, start)| *start);
+    Ok(starts)
+}
+
+fn lap_info(lap: &Lap, segment: Option<u32>) -> Option<LapInfo> {
+    Some(LapInfo {
+        lap_number: lap.lap_number,
+        date_start: lap.date_start?,
+        lap_duration: lap.lap_duration?,
+        duration_sector_1: lap.duration_sector_1,
+        duration_sector_2: lap.duration_sector_2,
+        duration_sector_3: lap.duration_sector_3,
+        is_pit_out_lap: lap.is_pit_out_lap.unwrap_or(false),
+        segment,
+    })
+}
+
+fn fastest(laps: impl Iterator<Item = LapInfo>) -> Option<LapInfo> {
+    laps.min_by(|a, b| a.lap_duration.total_cmp(&b.lap_duration))
+}
+
+/// Picks a timed lap of the driver. Laps without a start or a duration are
+/// never selected.
+pub async fn select_lap(
+    state: &AppState,
+    session_key: u32,
+    driver_number: u32,
+    selection: LapSelection,
+) -> Result<LapInfo, Error> {
+    let laps = archive::laps(state, session_key, Some(driver_number)).await?;
+    let timed = || laps.iter().filter_map(|lap| lap_info(lap, None));
+
+    let lap = match selection {
+        LapSelection::Number(number) => timed().find(|lap| lap.lap_number == number),
+        LapSelection::Fastest => fastest(timed()),
+        LapSelection::Latest => {
+            let best = fastest(timed().filter(|lap| !lap.is_pit_out_lap));
+            best.and_then(|best| {
+                let limit = best.lap_duration * REPRESENTATIVE_LAP_RATIO;
+                timed()
+                    .filter(|lap| !lap.is_pit_out_lap && lap.lap_duration <= limit)
+                    .max_by_key(|lap| lap.date_start)
+            })
+        }
+        LapSelection::FastestInSegment(segment) => {
+            let starts = segment_starts(state, session_key).await?;
+            let Some(idx) = starts.iter().position(|(phase, _)| *phase == segment) else {
+                return Err(Error::new(
+                    StatusCode::NOT_FOUND,
+                    &format!("No Q{} segment found for this session", segment),
+                ));
+            };
+            let from = starts[idx].1;
+            let until = starts.get(idx + 1).map(|(_, start)| *start);
+
+            fastest(
+                timed()
+                    .filter(|lap| {
+                        lap.date_start >= from && until.is_none_or(|until| lap.date_start < until)
+                    })
+                    .map(|lap| LapInfo {
+                        segment: Some(segment),
+                        ..lap
+                    }),
+            )
+        }
+    };
+
+    lap.ok_or_else(|| Error::new(StatusCode::NOT_FOUND, "No valid lap found"))
+}
+
+/// Selects a lap of the driver and builds its distance aligned trace.
+pub async fn selected_lap_trace(
+    state: &AppState,
+    session_key: u32,
+    driver_number: u32,
+    selection: LapSelection,
+) -> Result<LapTrace, Error> {
+    let lap = select_lap(state, session_key, driver_number, selection).await?;
+    let points = lap_trace(
+        state,
+        session_key,
+        driver_number,
+        lap.date_start,
+        lap.lap_duration,
+    )
+    .await?;
+
+    Ok(LapTrace { lap, points })
+}
+
+/// Places every car_data sample on the lap by interpolating the distance
+/// covered between the two location samples around it. Both inputs must be
+/// sorted by date.
+pub fn align(car_data: &[CarData], locations: &[Location]) -> Vec<TracePoint> {
+    let Some(first) = locations.first() else {
+        return Vec::new();
+    };
+
+    let mut distances = Vec::with_capacity(locations.len());
+    let mut cumulative = 0.0;
+    let mut prev = first;
+    for loc in locations {
+        cumulative +=
+            ((loc.x - prev.x).powi(2) + (loc.y - prev.y).powi(2) + (loc.z - prev.z).powi(2)).sqrt();
+        distances.push(cumulative / LOCATION_UNITS_PER_METRE);
+        prev = loc;
+    }
+
+    let mut trace = Vec::with_capacity(car_data.len());
+    let mut idx = 0;
+    for sample in car_data {
+        while idx + 1 < locations.len() && locations[idx + 1].date <= sample.date {
+            idx += 1;
+        }
+
+        let (a, b) = (
+            &locations[idx],
+            locations.get(idx + 1).unwrap_or(&locations[idx]),
+        );
+        let span = (b.date - a.date).num_milliseconds();
+        let t = if span > 0 {
+            ((sample.date - a.date).num_milliseconds() as f64 / span as f64).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let d_b = distances.get(idx + 1).copied().unwrap_or(distances[idx]);
+
+        trace.push(TracePoint {
+            date: sample.date,
+            distance: distances[idx] + (d_b - distances[idx]) * t,
+            x: a.x + (b.x - a.x) * t,
+            y: a.y + (b.y - a.y) * t,
+            speed: sample.speed,
+            throttle: sample.throttle,
+            brake: sample.brake,
+            n_gear: sample.n_gear,
+            rpm: sample.rpm,
+            drs: sample.drs,
+        });
+    }
+
+    trace
+}
+
+/// Telemetry of a driver's lap starting at `start`, aligned to the distance
+/// covered since the start of the lap.
+pub async fn lap_trace(
+    state: &AppState,
+    session_key: u32,
+    driver_number: u32,
+    start: DateTime<Utc>,
+    duration: f64,
+) -> Result<Vec<TracePoint>, Error> {
+    let window = Some((
+        start,
+        start + Duration::milliseconds((duration * 1000.0) as i64),
+    ));
+
+    let mut locations = archive::location(state, session_key, driver_number, window).await?;
+    locations.sort_by_key(|l| l.date);
+    let mut car_data = archive::car_data(state, session_key, driver_number, window).await?;
+    car_data.sort_by_key(|c| c.date);
+
+    Ok(align(&car_data, &locations))
+}
+
+/// Keeps at most one point per `step` metres, always including the first.
+pub fn downsample(trace: &[TracePoint], step: Option<f64>) -> Vec<&TracePoint> {
+    let Some(step) = step else {
+        return trace.iter().collect();
+    };
+
+    let mut kept = Vec::new();
+    let mut next = f64::MIN;
+    for point in trace {
+        if point.distance >= next {
+            kept.push(point);
+            next = point.distance + step;
+        }
+    }
+    kept
+}
+
+pub fn sample(point: &TracePoint, channels: &[Channel]) -> TelemetrySample {
+    let mut sample = TelemetrySample {
+        distance: point.distance,
+        ..Default::default()
+    };
+    for channel in channels {
+        match channel {
+            Channel::Speed => sample.speed = Some(point.speed),
+            Channel::Throttle => sample.throttle = point.throttle,
+            Channel::Brake => sample.brake = point.brake,
+            Channel::NGear => sample.n_gear = point.n_gear,
+            Channel::Rpm => sample.rpm = point.rpm,
+            Channel::Drs => sample.drs = point.drs,
+        }
+    }
+    sample
+}
+
+/// Seconds since the start of the lap at every trace point, keyed by distance.
+fn elapsed_by_distance(trace: &LapTrace, scale: f64) -> Vec<(f64, f64)> {
+    trace
+        .points
+        .iter()
+        .map(|p| {
+            let elapsed = (p.date - trace.lap.date_start).num_milliseconds() as f64 / 1000.0;
+            (p.distance * scale, elapsed)
+        })
+        .collect()
+}
+
+/// Linear interpolation of the elapsed time at `distance`. `samples` must be
+/// sorted by distance and `idx` is advanced as the distance grows.
+fn elapsed_at(samples: &[(f64, f64)], idx: &mut usize, distance: f64) -> f64 {
+    while *idx + 1 < samples.len() && samples[*idx + 1].0 < distance {
+        *idx += 1;
+    }
+    let (d_a, t_a) = samples[*idx];
+    let Some(&(d_b, t_b)) = samples.get(*idx + 1) else {
+        return t_a;
+    };
+    if d_b <= d_a {
+        return t_a;
+    }
+    t_a + (t_b - t_a) * ((distance - d_a) / (d_b - d_a)).clamp(0.0, 1.0)
+}
+
+/// Time gap of `b` to `a` every `step` metres. Both laps are scaled to the
+/// length of `a` so different racing lines still end at the lap time
+/// difference.
+pub fn delta(a: &LapTrace, b: &LapTrace, step: f64) -> Vec<DeltaPoint> {
+    let (Some(end_a), Some(end_b)) = (a.points.last(), b.points.last()) else {
+        return Vec::new();
+    };
+    if end_a.distance <= 0.0 || end_b.distance <= 0.0 {
+        return Vec::new();
+    }
+
+    let samples_a = elapsed_by_distance(a, 1.0);
+    let samples_b = elapsed_by_distance(b, end_a.distance / end_b.distance);
+
+    let (mut idx_a, mut idx_b) = (0, 0);
+    let mut points = Vec::new();
+    let mut distance: f64 = 0.0;
+    loop {
+        let distance_at = distance.min(end_a.distance);
+        let t_a = elapsed_at(&samples_a, &mut idx_a, distance_at);
+        let t_b = elapsed_at(&samples_b, &mut idx_b, distance_at);
+        points.push(DeltaPoint {
+            distance: distance_at,
+            delta: t_b - t_a,
+        });
+
+        if distance >= end_a.distance {
+            break;
+        }
+        distance += step;
+    }
+    points
+}
+
+/// Splits the laps into `count` minisectors of equal length and times every
+/// driver through each of them. Every lap is scaled to the length of the first
+/// one, so drivers are compared over the same stretch of track however many
+/// samples their laps have.
+pub fn minisectors(laps: &[(u32, LapTrace)], count: u32) -> Vec<Minisector> {
+    let Some((_, reference)) = laps.first() else {
+        return Vec::new();
+    };
+    let Some(length) = reference.points.last().map(|p| p.distance) else {
+        return Vec::new();
+    };
+    if length <= 0.0 || count == 0 {
+        return Vec::new();
+    }
+
+    let mut samples = Vec::with_capacity(laps.len());
+    for (driver_number, lap) in laps {
+        match lap.points.last() {
+            Some(end) if end.distance > 0.0 => samples.push((
+                *driver_number,
+                elapsed_by_distance(lap, length / end.distance),
+                0,
+            )),
+            _ => {}
+        }
+    }
+
+    let sector_length = length / count as f64;
+    let mut ref_idx = 0;
+    let mut result = Vec::with_capacity(count as usize);
+    for i in 0..count {
+        let start = i as f64 * sector_length;
+        let end = start + sector_length;
+
+        let mut times: Vec<MinisectorTime> = samples
+            .iter_mut()
+            .map(|(driver_number, samples, idx)| {
+                let entry = elapsed_at(samples, idx, start);
+                MinisectorTime {
+                    driver_number: *driver_number,
+                    time: elapsed_at(samples, idx, end) - entry,
+                }
+            })
+            .collect();
+        times.sort_by(|a, b| a.time.total_cmp(&b.time));
+
+        let Some(fastest) = times.first() else {
+            break;
+        };
+        let time_gained = times.get(1).map_or(0.0, |next| next.time - fastest.time);
+
+        while ref_idx + 1 < reference.points.len()
+            && reference.points[ref_idx + 1].distance <= start
+        {
+            ref_idx += 1;
+        }
+        let origin = &reference.points[ref_idx];
+
+        result.push(Minisector {
+            minisector: i,
+            start_distance: start,
+            end_distance: end,
+            x: origin.x,
+            y: origin.y,
+            fastest_driver: fastest.driver_number,
+            time_gained,
+            times,
+        });
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    /// Location `x` metres along a straight line.
+    fn location(ms: i64, x: f64) -> Location {
+        Location {
+            date: at(ms),
+            driver_number: 1,
+            x: x * LOCATION_UNITS_PER_METRE,
+            y: 0.0,
+            z: 0.0,
+        }
+    }
+
+    fn car_data(ms: i64) -> CarData {
+        CarData {
+            date: at(ms),
+            driver_number: 1,
+            speed: 200.0,
+            throttle: Some(100.0),
+            brake: Some(0.0),
+            n_gear: Some(7),
+            rpm: Some(11000),
+            drs: Some(0),
+        }
+    }
+
+    fn point(ms: i64, distance: f64) -> TracePoint {
+        TracePoint {
+            date: at(ms),
+            distance,
+            x: distance,
+            y: 0.0,
+            speed: 200.0,
+            throttle: None,
+            brake: None,
+            n_gear: None,
+            rpm: None,
+            drs: None,
+        }
+    }
+
+    /// Lap starting at `at(0)` through (milliseconds, metres) points.
+    fn lap(points: &[(i64, f64)]) -> LapTrace {
+        LapTrace {
+            lap: LapInfo {
+                lap_number: 1,
+                date_start: at(0),
+                lap_duration: points.last().map_or(0.0, |(ms, _)| *ms as f64 / 1000.0),
+                duration_sector_1: None,
+                duration_sector_2: None,
+                duration_sector_3: None,
+                is_pit_out_lap: false,
+                segment: None,
+            },
+            points: points.iter().map(|(ms, d)| point(*ms, *d)).collect(),
+        }
+    }
+
+    fn rounded(x: f64) -> f64 {
+        (x * 1000.0).round() / 1000.0
+    }
+
+    #[test]
+    fn align_interpolates_car_data_between_locations() {
+        // Locations every 200 ms, car data every 100 ms and past both ends
+        let locations = [location(0, 0.0), location(200, 10.0), location(400, 30.0)];
+        let car_data: Vec<_> = [-100, 0, 100, 200, 300, 400, 500]
+            .into_iter()
+            .map(car_data)
+            .collect();
+
+        let distances: Vec<_> = align(&car_data, &locations)
+            .iter()
+            .map(|p| rounded(p.distance))
+            .collect();
+        assert_eq!(distances, vec![0.0, 0.0, 5.0, 10.0, 20.0, 30.0, 30.0]);
+    }
+
+    #[test]
+    fn align_needs_both_traces() {
+        assert!(align(&[car_data(0)], &[]).is_empty());
+        assert!(align(&[], &[location(0, 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn downsample_keeps_one_point_per_step() {
+        let trace: Vec<_> = [0.0, 0.5, 1.0, 3.0, 3.5, 10.0]
+            .into_iter()
+            .map(|d| point(0, d))
+            .collect();
+        let distances = |step| -> Vec<f64> {
+            downsample(&trace, step)
+                .iter()
+                .map(|p| p.distance)
+                .collect()
+        };
+
+        assert_eq!(distances(None).len(), trace.len());
+        assert_eq!(distances(Some(2.0)), vec![0.0, 3.0, 10.0]);
+        // A step longer than the lap keeps the first point only
+        assert_eq!(distances(Some(100.0)), vec![0.0]);
+        assert!(downsample(&[], Some(2.0)).is_empty());
+    }
+
+    fn deltas(a: &LapTrace, b: &LapTrace, step: f64) -> Vec<(f64, f64)> {
+        delta(a, b, step)
+            .iter()
+            .map(|p| (rounded(p.distance), rounded(p.delta)))
+            .collect()
+    }
+
+    #[test]
+    fn delta_compares_laps_at_equal_distances() {
+        let a = lap(&[(0, 0.0), (10_000, 100.0)]);
+        // Sampled twice as often, and a second slower
+        let b = lap(&[(0, 0.0), (5_500, 50.0), (11_000, 100.0)]);
+
+        assert_eq!(
+            deltas(&a, &b, 50.0),
+            vec![(0.0, 0.0), (50.0, 0.5), (100.0, 1.0)]
+        );
+        // A step longer than the lap still ends at the lap time difference
+        assert_eq!(deltas(&a, &b, 500.0), vec![(0.0, 0.0), (100.0, 1.0)]);
+    }
+
+    #[test]
+    fn delta_scales_laps_to_the_same_length() {
+        let a = lap(&[(0, 0.0), (10_000, 100.0)]);
+        // A wider line over 110 m
+        let b = lap(&[(0, 0.0), (11_000, 110.0)]);
+
+        assert_eq!(
+            deltas(&a, &b, 50.0),
+            vec![(0.0, 0.0), (50.0, 0.5), (100.0, 1.0)]
+        );
+    }
+
+    #[test]
+    fn delta_of_an_empty_lap_is_empty() {
+        let a = lap(&[(0, 0.0), (10_000, 100.0)]);
+        assert!(delta(&a, &lap(&[]), 10.0).is_empty());
+        assert!(delta(&lap(&[]), &a, 10.0).is_empty());
+        assert!(delta(&a, &lap(&[(0, 0.0)]), 10.0).is_empty());
+    }
+
+    #[test]
+    fn minisectors_time_every_driver_over_the_same_stretch() {
+        let laps = [
+            (1, lap(&[(0, 0.0), (5_000, 50.0), (10_000, 100.0)])),
+            // Slower in the second half, sampled at a different rate
+            (
+                44,
+                lap(&[(0, 0.0), (2_500, 25.0), (5_000, 50.0), (11_000, 100.0)]),
+            ),
+            // Nothing to time
+            (63, lap(&[])),
+        ];
+
+        let sectors = minisectors(&laps, 2);
+        assert_eq!(sectors.len(), 2);
+
+        assert_eq!(
+            (sectors[0].start_distance, sectors[0].end_distance),
+            (0.0, 50.0)
+        );
+        assert_eq!(rounded(sectors[0].time_gained), 0.0);
+        assert_eq!(sectors[0].times.len(), 2);
+
+        assert_eq!(sectors[1].x, 50.0);
+        assert_eq!(sectors[1].fastest_driver, 1);
+        assert_eq!(rounded(sectors[1].time_gained), 1.0);
+        let times: Vec<_> = sectors[1]
+            .times
+            .iter()
+            .map(|t| (t.driver_number, rounded(t.time)))
+            .collect();
+        assert_eq!(times, vec![(1, 5.0), (44, 6.0)]);
+    }
+
+    #[test]
+    fn minisectors_need_a_reference_lap_and_a_count() {
+        let laps = [(1, lap(&[(0, 0.0), (10_000, 100.0)]))];
+        assert!(minisectors(&laps, 0).is_empty());
+        assert!(minisectors(&[], 10).is_empty());
+        assert!(minisectors(&[(1, lap(&[]))], 10).is_empty());
+    }
+}
@@ -2,6 +2,16 @@
 pub struct Config {
     pub db_url: String,
     pub jwt_secret: String,
+    pub jolpica_base_url: String,
+    pub openf1_base_url: String,
+    // 0 disables the background calendar sync
+    pub calendar_sync_interval_secs: u64,
+    // 0 disables the background session key reconciler
+    pub session_key_sync_interval_secs: u64,
+    // 0 disables the sweeper, expired cache entries are then only dropped on read
+    pub cache_sweep_interval_secs: u64,
+    // 0 disables archiving finished sessions in the background
+    pub session_archive_interval_secs: u64,
 }
 
 impl Config {
@@ -9,6 +19,26 @@ impl Config {
         Config {
             db_url: std::env::var("DATABASE_URL").expect("DB_URL not set"),
             jwt_secret: std::env::var("JWT_SECRET").expect("JWT_SECRET not set"),
+            jolpica_base_url: std::env::var("JOLPICA_BASE_URL")
+                .unwrap_or_else(|_| "https://api.jolpi.ca/ergast/f1".to_string()),
+            openf1_base_url: std::env::var("OPENF1_BASE_URL")
+                .unwrap_or_else(|_| "https://api.openf1.org/v1".to_string()),
+            calendar_sync_interval_secs: std::env::var("CALENDAR_SYNC_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(6 * 60 * 60),
+            session_key_sync_interval_secs: std::env::var("SESSION_KEY_SYNC_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5 * 60),
+            cache_sweep_interval_secs: std::env::var("CACHE_SWEEP_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+            session_archive_interval_secs: std::env::var("SESSION_ARCHIVE_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60 * 60),
         }
     }
 }
@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::models::fantasy::{
+    FantasyAsset, FantasyTeamRound, ASSET_CONSTRUCTOR, ASSET_DRIVER, CHIP_LIMITLESS, CHIP_WILDCARD,
+};
+
+pub const DRIVERS_PER_TEAM: usize = 5;
+pub const CONSTRUCTORS_PER_TEAM: usize = 2;
+pub const BUDGET_CAP: f64 = 100.0;
+pub const FREE_TRANSFERS_PER_ROUND: i32 = 2;
+pub const MAX_ROLLOVER_TRANSFERS: i32 = 1;
+pub const EXTRA_TRANSFER_PENALTY: f64 = 10.0;
+
+/// Price each pick counts at: what was paid for it when it is still held
+/// from `held`, the current price of the asset otherwise.
+pub fn purchase_prices(
+    assets: &[FantasyAsset],
+    ids: &[i32],
+    held: &HashMap<i32, f64>,
+) -> HashMap<i32, f64> {
+    ids.iter()
+        .filter_map(|id| {
+            let asset = assets.iter().find(|a| a.id == *id)?;
+            Some((*id, held.get(id).copied().unwrap_or(asset.price)))
+        })
+        .collect()
+}
+
+/// Checks a set of picks against the priced assets of the season and
+/// returns the total team value. Assets kept from `held` count at their
+/// purchase price, so price rises never push a saved team over the cap.
+pub fn validate_picks(
+    assets: &[FantasyAsset],
+    driver_ids: &[i32],
+    constructor_ids: &[i32],
+    held: &HashMap<i32, f64>,
+    budget_cap: f64,
+) -> Result<f64, String> {
+    if driver_ids.len() != DRIVERS_PER_TEAM {
+        return Err(format!("A team needs exactly {} drivers", DRIVERS_PER_TEAM));
+    }
+    if constructor_ids.len() != CONSTRUCTORS_PER_TEAM {
+        return Err(format!(
+            "A team needs exactly {} constructors",
+            CONSTRUCTORS_PER_TEAM
+        ));
+    }
+
+    let unique_drivers: HashSet<_> = driver_ids.iter().collect();
+    let unique_constructors: HashSet<_> = constructor_ids.iter().collect();
+    if unique_drivers.len() != driver_ids.len()
+        || unique_constructors.len() != constructor_ids.len()
+    {
+        return Err("The same asset cannot be picked twice".to_string());
+    }
+
+    let mut team_value = 0.0;
+    for (ids, asset_type) in [
+        (driver_ids, ASSET_DRIVER),
+        (constructor_ids, ASSET_CONSTRUCTOR),
+    ] {
+        for id in ids {
+            let asset = assets
+                .iter()
+                .find(|a| a.id == *id && a.asset_type == asset_type)
+                .ok_or_else(|| format!("Unknown {} asset {}", asset_type, id))?;
+            team_value += held.get(id).copied().unwrap_or(asset.price);
+        }
+    }
+
+    if team_value > budget_cap {
+        return Err(format!(
+            "Team value {:.1} exceeds the budget cap of {:.1}",
+            team_value, budget_cap
+        ));
+    }
+
+    Ok(team_value)
+}
+
+/// Free transfers available in `round`, given the team as it was picked for
+/// the closest earlier round. Unused free transfers roll over up to a limit.
+pub fn free_transfers(previous: Option<&FantasyTeamRound>, round: i32) -> i32 {
+    let rollover = match previous {
+        None => 0,
+        // Every skipped round left all of its free transfers unused
+        Some(prev) if prev.round < round - 1 => MAX_ROLLOVER_TRANSFERS,
+        Some(prev) => (prev.free_transfers - prev.transfers_made).clamp(0, MAX_ROLLOVER_TRANSFERS),
+    };
+    FREE_TRANSFERS_PER_ROUND + rollover
+}
+
+/// Pairs the assets dropped from `before` with the ones added in `after`.
+pub fn swapped_assets(before: &[i32], after: &[i32]) -> Vec<(i32, i32)> {
+    let removed = before.iter().filter(|id| !after.contains(id));
+    let added = after.iter().filter(|id| !before.contains(id));
+    removed.zip(added).map(|(out, new)| (*out, *new)).collect()
+}
+
+/// Point deduction for transfers beyond the free allowance. Wildcard and
+/// limitless rounds have unlimited free transfers.
+pub fn transfer_penalty(transfers_made: i32, free_transfers: i32, chip: Option<&str>) -> f64 {
+    if matches!(chip, Some(CHIP_WILDCARD | CHIP_LIMITLESS)) {
+        return 0.0;
+    }
+    (transfers_made - free_transfers).max(0) as f64 * EXTRA_TRANSFER_PENALTY
+}
+
+/// Budget cap for a round, lifted entirely by the limitless chip.
+pub fn budget_cap(chip: Option<&str>) -> f64 {
+    if chip == Some(CHIP_LIMITLESS) {
+        f64::INFINITY
+    } else {
+        BUDGET_CAP
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use sqlx::types::Json;
+
+    use super::*;
+    use crate::models::fantasy::CHIP_EXTRA_BOOST;
+
+    fn asset(id: i32, asset_type: &str, price: f64) -> FantasyAsset {
+        FantasyAsset {
+            id,
+            season: "2025".to_string(),
+            asset_type: asset_type.to_string(),
+            external_ref: id.to_string(),
+            name: format!("Asset {}", id),
+            constructor_ref: None,
+            price,
+        }
+    }
+
+    /// Drivers 1-6 and constructors 101-103, 10.0 each.
+    fn assets() -> Vec<FantasyAsset> {
+        let drivers = (1..=6).map(|id| asset(id, ASSET_DRIVER, 10.0));
+        let constructors = (101..=103).map(|id| asset(id, ASSET_CONSTRUCTOR, 10.0));
+        drivers.chain(constructors).collect()
+    }
+
+    fn picked(round: i32, free_transfers: i32, transfers_made: i32) -> FantasyTeamRound {
+        FantasyTeamRound {
+            team_id: 1,
+            round,
+            driver_ids: vec![1, 2, 3, 4, 5],
+            constructor_ids: vec![101, 102],
+            team_value: 70.0,
+            free_transfers,
+            transfers_made,
+            penalty_points: 0.0,
+            chip: None,
+            boost_asset_id: None,
+            purchase_prices: Json(HashMap::new()),
+            updated_at: Utc::now(),
+        }
+    }
+
+    /// Validates fresh picks, none held from an earlier round.
+    fn check(drivers: &[i32], constructors: &[i32], cap: f64) -> Result<f64, String> {
+        validate_picks(&assets(), drivers, constructors, &HashMap::new(), cap)
+    }
+
+    #[test]
+    fn valid_picks_return_the_team_value() {
+        assert_eq!(check(&[1, 2, 3, 4, 5], &[101, 102], BUDGET_CAP), Ok(70.0));
+    }
+
+    #[test]
+    fn picks_must_fill_every_slot_once() {
+        assert!(check(&[1, 2, 3, 4], &[101, 102], BUDGET_CAP).is_err());
+        assert!(check(&[1, 2, 3, 4, 5], &[101], BUDGET_CAP).is_err());
+        assert!(check(&[1, 1, 2, 3, 4], &[101, 102], BUDGET_CAP).is_err());
+    }
+
+    #[test]
+    fn picks_must_be_known_assets_of_the_right_type() {
+        // 101 is a constructor
+        assert!(check(&[1, 2, 3, 4, 101], &[102, 103], BUDGET_CAP).is_err());
+        assert!(check(&[1, 2, 3, 4, 99], &[101, 102], BUDGET_CAP).is_err());
+    }
+
+    #[test]
+    fn budget_cap_is_inclusive_and_lifted_by_limitless() {
+        assert_eq!(check(&[1, 2, 3, 4, 5], &[101, 102], 70.0), Ok(70.0));
+        assert!(check(&[1, 2, 3, 4, 5], &[101, 102], 69.9).is_err());
+
+        assert_eq!(budget_cap(None), BUDGET_CAP);
+        assert_eq!(budget_cap(Some(CHIP_WILDCARD)), BUDGET_CAP);
+        assert_eq!(budget_cap(Some(CHIP_LIMITLESS)), f64::INFINITY);
+    }
+
+    #[test]
+    fn held_assets_count_at_their_purchase_price() {
+        // Driver 1 rose from 8.0 and driver 6 is new at 10.0
+        let mut assets = assets();
+        assets[0].price = 12.0;
+        let held = HashMap::from([(1, 8.0), (2, 10.0)]);
+
+        let drivers = [1, 2, 3, 4, 6];
+        assert_eq!(
+            validate_picks(&assets, &drivers, &[101, 102], &held, 68.0),
+            Ok(68.0)
+        );
+        assert!(check(&drivers, &[101, 102], 68.0).is_err());
+
+        let prices = purchase_prices(&assets, &drivers, &held);
+        assert_eq!(prices[&1], 8.0);
+        assert_eq!(prices[&6], 10.0);
+    }
+
+    #[test]
+    fn unused_free_transfers_roll_over_up_to_the_limit() {
+        assert_eq!(free_transfers(None, 1), FREE_TRANSFERS_PER_ROUND);
+        // One of two unused
+        assert_eq!(free_transfers(Some(&picked(3, 2, 1)), 4), 3);
+        // Both unused, only one rolls over
+        assert_eq!(free_transfers(Some(&picked(3, 2, 0)), 4), 3);
+        // Over the allowance, nothing rolls over
+        assert_eq!(free_transfers(Some(&picked(3, 2, 4)), 4), 2);
+        // Skipped rounds never used theirs
+        assert_eq!(free_transfers(Some(&picked(1, 2, 2)), 4), 3);
+    }
+
+    #[test]
+    fn transfers_beyond_the_allowance_are_penalised() {
+        assert_eq!(transfer_penalty(2, 2, None), 0.0);
+        assert_eq!(transfer_penalty(4, 2, None), 2.0 * EXTRA_TRANSFER_PENALTY);
+        assert_eq!(
+            transfer_penalty(4, 2, Some(CHIP_EXTRA_BOOST)),
+            2.0 * EXTRA_TRANSFER_PENALTY
+        );
+        assert_eq!(transfer_penalty(4, 2, Some(CHIP_WILDCARD)), 0.0);
+        assert_eq!(transfer_penalty(4, 2, Some(CHIP_LIMITLESS)), 0.0);
+    }
+
+    #[test]
+    fn swaps_pair_removed_and_added_assets() {
+        assert_eq!(swapped_assets(&[1, 2, 3], &[3, 2, 6]), vec![(1, 6)]);
+        assert!(swapped_assets(&[1, 2], &[2, 1]).is_empty());
+    }
+}
@@ -2,4 +2,5 @@ pub mod state;
 pub mod hash_password;
 pub mod config;
 pub mod jwt_encode;
-pub mod race_utils;
\ No newline at end of file
+pub mod race_utils;
+pub mod fantasy;
\ No newline at end of file
@@ -1,12 +1,20 @@
+use chrono::NaiveTime;
+
 pub fn map_session_name(external: &str) -> Option<&'static str> {
- match external {
-        "Practice 1" => Some("FirstPractice"),
-        "Practice 2" => Some("SecondPractice"),
-        "Practice 3" => Some("ThirdPractice"),
+    match external.trim() {
+        "Practice 1" | "Free Practice 1" => Some("FirstPractice"),
+        "Practice 2" | "Free Practice 2" => Some("SecondPractice"),
+        "Practice 3" | "Free Practice 3" => Some("ThirdPractice"),
         "Qualifying" => Some("Qualifying"),
-        "Sprint Qualifying" => Some("SprintQualifying"),
-        "Sprint" => Some("Sprint"),
-        "Race" => Some("Race"), 
+        // Sprint qualifying was called Sprint Shootout in 2023
+        "Sprint Qualifying" | "Sprint Shootout" => Some("SprintQualifying"),
+        "Sprint" | "Sprint Race" => Some("Sprint"),
+        "Race" => Some("Race"),
         _ => None,
     }
-}
\ No newline at end of file
+}
+
+/// Parses Jolpica session times, which look like "14:00:00Z".
+pub fn parse_jolpica_time(time: Option<&str>) -> Option<NaiveTime> {
+    time.and_then(|t| NaiveTime::parse_from_str(t.trim_end_matches('Z'), "%H:%M:%S").ok())
+}
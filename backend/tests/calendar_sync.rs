@@ -0,0 +1,83 @@
+//! Runs the calendar sync against a local stub of the Jolpica API.
+//!
+//! Needs a Postgres database in `TEST_DATABASE_URL`, so it is ignored by
+//! default. Run it with `cargo test -- --ignored` or `make test-backend-db`.
+//! Every run works in its own schema, which is dropped at the end.
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use axum::{extract::State, Json, Router};
+use backend::services::calendar_sync::sync_calendar;
+use serde_json::Value;
+use tokio::net::TcpListener;
+
+const FIXTURE: &str = include_str!("fixtures/jolpica_races_2025.json");
+
+/// Serves whatever payload is current for every request.
+async fn serve_jolpica(payload: Arc<Mutex<Value>>) -> String {
+    async fn races(State(payload): State<Arc<Mutex<Value>>>) -> Json<Value> {
+        Json(payload.lock().unwrap().clone())
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = Router::new().fallback(races).with_state(payload);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+async fn second_sync_is_a_no_op_and_reschedules_are_reported() {
+    let db_url = common::database_url();
+    let schema = format!("calendar_sync_{}", uuid::Uuid::new_v4().simple());
+    let pool = common::test_pool(&db_url, &schema, common::CALENDAR_SCHEMA).await;
+
+    let payload = Arc::new(Mutex::new(serde_json::from_str::<Value>(FIXTURE).unwrap()));
+    let base_url = serve_jolpica(payload.clone()).await;
+    let state = common::test_state(pool.clone(), base_url);
+
+    let first = sync_calendar(&state, "2025").await.unwrap();
+    assert_eq!(first.circuits_changed, 2);
+    assert_eq!(first.races_changed, 2);
+    assert_eq!(first.sessions_changed, 10);
+
+    let second = sync_calendar(&state, "2025").await.unwrap();
+    assert_eq!(second.circuits_changed, 0);
+    assert_eq!(second.races_changed, 0);
+    assert_eq!(second.sessions_changed, 0);
+    assert!(second.changes.is_empty(), "{:?}", second.changes);
+
+    // Qualifying of round 1 moves back an hour
+    payload.lock().unwrap()["MRData"]["RaceTable"]["Races"][0]["Qualifying"]["time"] =
+        Value::from("06:00:00Z");
+
+    let rescheduled = sync_calendar(&state, "2025").await.unwrap();
+    assert_eq!(rescheduled.races_changed, 0);
+    assert_eq!(rescheduled.sessions_changed, 1);
+    assert_eq!(rescheduled.changes.len(), 1);
+    assert!(
+        rescheduled.changes[0].starts_with("Round 1 Qualifying rescheduled"),
+        "{}",
+        rescheduled.changes[0]
+    );
+
+    let time: Option<chrono::NaiveTime> = sqlx::query_scalar(
+        r#"
+        SELECT s."time"
+        FROM "Sessions" s
+        JOIN "Races" r ON r.id = s."raceId"
+        WHERE r.round = '1' AND s."sessionType" = 'Qualifying'
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(time, chrono::NaiveTime::from_hms_opt(6, 0, 0));
+
+    pool.close().await;
+    common::drop_schema(&db_url, &schema).await;
+}
@@ -0,0 +1,103 @@
+//! Helpers shared by the database tests.
+
+// Every test binary uses a different subset
+#![allow(dead_code)]
+
+use backend::{
+    clients::{jolpica::JolpicaClient, openf1::OpenF1Client},
+    services::cache::Cache,
+    utils::{config::Config, state::AppState},
+};
+use sqlx::{postgres::PgPoolOptions, Executor, PgPool};
+
+// The legacy calendar tables are not created by the migrations
+pub const CALENDAR_SCHEMA: &str = r#"
+CREATE TABLE "Circuits" (
+    "circuitId" TEXT PRIMARY KEY,
+    "circuitName" TEXT,
+    locality TEXT,
+    country TEXT,
+    lat TEXT,
+    long TEXT
+);
+CREATE TABLE "Races" (
+    id BIGSERIAL PRIMARY KEY,
+    created_at TIMESTAMPTZ DEFAULT now(),
+    season TEXT NOT NULL,
+    round TEXT NOT NULL,
+    date DATE,
+    time TIME,
+    "raceName" TEXT NOT NULL,
+    "circuitId" TEXT NOT NULL
+);
+CREATE TABLE "Sessions" (
+    id SERIAL PRIMARY KEY,
+    "raceId" BIGINT NOT NULL,
+    "sessionType" TEXT NOT NULL,
+    "date" DATE,
+    "time" TIME,
+    session_key INT,
+    meeting_key INT
+);
+"#;
+
+/// `TEST_DATABASE_URL`, panicking when it is not set.
+pub fn database_url() -> String {
+    std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL is not set")
+}
+
+/// Pool whose connections work in a new `schema`, set up with `ddl`.
+pub async fn test_pool(db_url: &str, schema: &str, ddl: &str) -> PgPool {
+    let admin = PgPool::connect(db_url).await.unwrap();
+    admin
+        .execute(format!(r#"CREATE SCHEMA "{schema}""#).as_str())
+        .await
+        .unwrap();
+
+    let search_path = format!(r#"SET search_path TO "{schema}""#);
+    let pool = PgPoolOptions::new()
+        .max_connections(2)
+        .after_connect(move |conn, _| {
+            let search_path = search_path.clone();
+            Box::pin(async move {
+                conn.execute(search_path.as_str()).await?;
+                Ok(())
+            })
+        })
+        .connect(db_url)
+        .await
+        .unwrap();
+    pool.execute(ddl).await.unwrap();
+    pool
+}
+
+pub async fn drop_schema(db_url: &str, schema: &str) {
+    let admin = PgPool::connect(db_url).await.unwrap();
+    admin
+        .execute(format!(r#"DROP SCHEMA "{schema}" CASCADE"#).as_str())
+        .await
+        .unwrap();
+}
+
+pub fn test_state(db_pool: PgPool, jolpica_base_url: String) -> AppState {
+    let config = Config {
+        db_url: String::new(),
+        jwt_secret: String::new(),
+        jolpica_base_url,
+        openf1_base_url: String::new(),
+        calendar_sync_interval_secs: 0,
+        session_key_sync_interval_secs: 0,
+        cache_sweep_interval_secs: 0,
+        session_archive_interval_secs: 0,
+    };
+    let http_client = reqwest::Client::new();
+
+    AppState {
+        openf1: OpenF1Client::new(http_client.clone(), &config.openf1_base_url),
+        jolpica: JolpicaClient::new(http_client.clone(), &config.jolpica_base_url),
+        cache: Cache::new(db_pool.clone()),
+        db_pool,
+        config,
+        http_client,
+    }
+}
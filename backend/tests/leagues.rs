@@ -0,0 +1,90 @@
+//! League membership against a Postgres database.
+//!
+//! Needs `TEST_DATABASE_URL`, see `calendar_sync.rs`.
+
+mod common;
+
+use std::sync::Arc;
+
+use axum::{extract::Path, extract::State, response::IntoResponse, Extension, Json};
+use backend::{
+    handlers::league::{join_league, kick_member},
+    models::{jwt::Claims, league::JoinLeaguePayload},
+};
+use http::StatusCode;
+
+const SCHEMA: &str = concat!(
+    include_str!("../migrations/0001_fantasy_teams.sql"),
+    include_str!("../migrations/0003_leagues.sql"),
+    include_str!("../migrations/0014_league_bans.sql"),
+);
+const OWNER: &str = "owner@example.com";
+const MEMBER: &str = "member@example.com";
+
+fn claims(email: &str) -> Extension<Claims> {
+    Extension(Claims {
+        sub: email.to_string(),
+        iat: 0,
+        exp: usize::MAX,
+    })
+}
+
+fn invite(code: &str) -> Json<JoinLeaguePayload> {
+    Json(JoinLeaguePayload {
+        invite_code: code.to_string(),
+    })
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+async fn kicked_members_cannot_rejoin_with_the_invite_code() {
+    let db_url = common::database_url();
+    let schema = format!("leagues_{}", uuid::Uuid::new_v4().simple());
+    let pool = common::test_pool(&db_url, &schema, SCHEMA).await;
+    let state = Arc::new(common::test_state(pool.clone(), String::new()));
+
+    let league_id: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO "Leagues" (name, season, invite_code, owner_email)
+        VALUES ('Test league', '2025', 'ABCD1234', $1)
+        RETURNING id
+        "#,
+    )
+    .bind(OWNER)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    let joined = join_league(State(state.clone()), claims(MEMBER), invite("abcd1234"))
+        .await
+        .unwrap();
+    assert_eq!(joined.into_response().status(), StatusCode::OK);
+
+    let kicked = kick_member(
+        State(state.clone()),
+        claims(OWNER),
+        Path((league_id, MEMBER.to_string())),
+    )
+    .await
+    .unwrap();
+    assert_eq!(kicked.into_response().status(), StatusCode::OK);
+
+    let Err(rejoin) = join_league(State(state.clone()), claims(MEMBER), invite("ABCD1234")).await
+    else {
+        panic!("kicked member joined again");
+    };
+    assert_eq!(rejoin.code, StatusCode::FORBIDDEN);
+
+    let members: i64 = sqlx::query_scalar(
+        r#"SELECT COUNT(*) FROM "LeagueMembers" WHERE league_id = $1 AND user_email = $2"#,
+    )
+    .bind(league_id)
+    .bind(MEMBER)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(members, 0);
+
+    pool.close().await;
+    common::drop_schema(&db_url, &schema).await;
+}
@@ -0,0 +1,110 @@
+//! Price history of `update_prices` against a Postgres database.
+//!
+//! Needs `TEST_DATABASE_URL`, see `calendar_sync.rs`.
+
+mod common;
+
+use backend::services::pricing::update_prices;
+use sqlx::PgPool;
+
+const SCHEMA: &str = concat!(
+    include_str!("../migrations/0001_fantasy_teams.sql"),
+    include_str!("../migrations/0002_fantasy_scoring.sql"),
+    include_str!("../migrations/0004_fantasy_transfers.sql"),
+    include_str!("../migrations/0005_fantasy_chips.sql"),
+    include_str!("../migrations/0006_fantasy_prices.sql"),
+    include_str!("../migrations/0015_purchase_prices.sql"),
+);
+
+async fn set_points(pool: &PgPool, asset_id: i32, round: i32, points: f64) {
+    sqlx::query(
+        r#"
+        INSERT INTO "FantasyPoints"
+            (season, round, session_type, asset_id, points, breakdown, rules_version)
+        VALUES ('2025', $1, 'Race', $2, $3, '{}', 1)
+        ON CONFLICT (season, round, session_type, asset_id)
+        DO UPDATE SET points = EXCLUDED.points
+        "#,
+    )
+    .bind(round)
+    .bind(asset_id)
+    .bind(points)
+    .execute(pool)
+    .await
+    .unwrap();
+}
+
+/// (price, delta) of every round of the history, and the current price.
+async fn prices(pool: &PgPool, asset_id: i32) -> (Vec<(f64, f64)>, f64) {
+    let history = sqlx::query_as(
+        r#"SELECT price, delta FROM "FantasyAssetPrices" WHERE asset_id = $1 ORDER BY round"#,
+    )
+    .bind(asset_id)
+    .fetch_all(pool)
+    .await
+    .unwrap();
+    let price = sqlx::query_scalar(r#"SELECT price FROM "FantasyAssets" WHERE id = $1"#)
+        .bind(asset_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    (history, price)
+}
+
+fn round_prices((history, price): (Vec<(f64, f64)>, f64)) -> (Vec<(f64, f64)>, f64) {
+    let tenths = |x: f64| (x * 10.0).round() / 10.0;
+    let history = history
+        .into_iter()
+        .map(|(price, delta)| (tenths(price), tenths(delta)))
+        .collect();
+    (history, tenths(price))
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+async fn rescoring_an_earlier_round_keeps_later_changes() {
+    let db_url = common::database_url();
+    let schema = format!("pricing_{}", uuid::Uuid::new_v4().simple());
+    let pool = common::test_pool(&db_url, &schema, SCHEMA).await;
+    let state = common::test_state(pool.clone(), String::new());
+
+    let asset_id: i32 = sqlx::query_scalar(
+        r#"
+        INSERT INTO "FantasyAssets" (season, asset_type, external_ref, name, price)
+        VALUES ('2025', 'driver', '1', 'Driver 1', 10.0)
+        RETURNING id
+        "#,
+    )
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+
+    // 1.2 points per million, +0.3, unowned, -0.1
+    set_points(&pool, asset_id, 1, 12.0).await;
+    update_prices(&state, "2025", 1).await.unwrap();
+    assert_eq!(
+        round_prices(prices(&pool, asset_id).await),
+        (vec![(10.2, 0.2)], 10.2)
+    );
+
+    // Still 12 points per round, now under 1.2 per million, +0.1 and -0.1
+    set_points(&pool, asset_id, 2, 12.0).await;
+    update_prices(&state, "2025", 2).await.unwrap();
+    assert_eq!(
+        round_prices(prices(&pool, asset_id).await),
+        (vec![(10.2, 0.2), (10.2, 0.0)], 10.2)
+    );
+
+    // Round 1 corrected to nothing, -0.3 and -0.1, round 2 keeps its change
+    set_points(&pool, asset_id, 1, 0.0).await;
+    update_prices(&state, "2025", 1).await.unwrap();
+    let expected = (vec![(9.6, -0.4), (9.6, 0.0)], 9.6);
+    assert_eq!(round_prices(prices(&pool, asset_id).await), expected);
+
+    // Running a round again changes nothing
+    update_prices(&state, "2025", 1).await.unwrap();
+    assert_eq!(round_prices(prices(&pool, asset_id).await), expected);
+
+    pool.close().await;
+    common::drop_schema(&db_url, &schema).await;
+}
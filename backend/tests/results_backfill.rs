@@ -0,0 +1,99 @@
+//! Backfill of final results against a Jolpica stub that fails or has
+//! nothing for every round.
+//!
+//! Needs `TEST_DATABASE_URL`, see `calendar_sync.rs`.
+
+mod common;
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+use backend::services::results::{archive_final_results, MAX_ATTEMPTS};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+/// Round 1 fails and round 2 has no results, counting every request.
+async fn serve_jolpica(requests: Arc<AtomicUsize>) -> String {
+    async fn failing(State(requests): State<Arc<AtomicUsize>>) -> StatusCode {
+        requests.fetch_add(1, Ordering::SeqCst);
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    async fn cancelled(State(requests): State<Arc<AtomicUsize>>) -> Json<Value> {
+        requests.fetch_add(1, Ordering::SeqCst);
+        Json(json!({ "MRData": { "total": "0", "RaceTable": { "Races": [] } } }))
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let app = Router::new()
+        .route("/2025/1/results/", get(failing))
+        .route("/2025/2/results/", get(cancelled))
+        .with_state(requests);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    format!("http://{addr}")
+}
+
+#[tokio::test]
+#[ignore = "needs a Postgres database in TEST_DATABASE_URL"]
+async fn failing_rounds_are_skipped_and_given_up_on() {
+    let db_url = common::database_url();
+    let schema = format!("results_backfill_{}", uuid::Uuid::new_v4().simple());
+    let ddl = [
+        common::CALENDAR_SCHEMA,
+        include_str!("../migrations/0008_race_results.sql"),
+        include_str!("../migrations/0009_session_results.sql"),
+        include_str!("../migrations/0016_missing_results.sql"),
+    ]
+    .concat();
+    let pool = common::test_pool(&db_url, &schema, &ddl).await;
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    let base_url = serve_jolpica(requests.clone()).await;
+    let state = common::test_state(pool.clone(), base_url);
+
+    sqlx::query(
+        r#"
+        WITH races AS (
+            INSERT INTO "Races" (season, round, date, "raceName", "circuitId")
+            VALUES
+                ('2025', '1', DATE '2025-03-16', 'Round 1', 'albert_park'),
+                ('2025', '2', DATE '2025-03-23', 'Round 2', 'shanghai')
+            RETURNING id, date
+        )
+        INSERT INTO "Sessions" ("raceId", "sessionType", "date", "time")
+        SELECT id, 'Race', date, TIME '04:00' FROM races
+        "#,
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Neither round aborts the backfill
+    assert_eq!(archive_final_results(&state, "2025").await.unwrap(), 0);
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let missing: Vec<(String, i32)> =
+        sqlx::query_as(r#"SELECT round, attempts FROM "MissingResults" ORDER BY round"#)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(missing, vec![("1".to_string(), 1), ("2".to_string(), 1)]);
+
+    for _ in 1..MAX_ATTEMPTS {
+        archive_final_results(&state, "2025").await.unwrap();
+    }
+    let polled = requests.load(Ordering::SeqCst);
+    assert_eq!(polled, 2 * MAX_ATTEMPTS as usize);
+
+    // Given up on, Jolpica is not asked again
+    archive_final_results(&state, "2025").await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), polled);
+
+    pool.close().await;
+    common::drop_schema(&db_url, &schema).await;
+}